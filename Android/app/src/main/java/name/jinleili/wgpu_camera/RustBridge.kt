@@ -25,7 +25,17 @@ class RustBridge private constructor() {
     external fun capture_one_frame(rustObj: Long)
     external fun start_capturing(rustObj: Long)
     external fun stop_capturing(rustObj: Long)
-    external fun enter_frame(rustObj: Long)
+    // timestamp: 单调时钟的秒数，用于推进滤镜切换的过渡动画
+    external fun enter_frame(rustObj: Long, timestamp: Double)
+    // JSON array of the filter schemas: id, name, input_param and params (name, type, min, max, default, step)
+    external fun filter_schemas(rustObj: Long): String
+    // The current filter chain as preset JSON, apply_preset returns false for an invalid preset
+    external fun export_preset(rustObj: Long, name: String): String
+    external fun apply_preset(rustObj: Long, json: String): Boolean
+    // 屏幕的旋转角度（0, 90, 180, 270），画面会据此保持正向
+    external fun set_device_rotation(rustObj: Long, rotation: Int)
+    // 前置摄像头的预览通常需要水平镜像，export 为 true 时设置的是输出帧的镜像
+    external fun set_mirror(rustObj: Long, horizontal: Boolean, vertical: Boolean, export: Boolean)
 
     external fun drop_camera_canvas(rustObj: Long)
 }
\ No newline at end of file
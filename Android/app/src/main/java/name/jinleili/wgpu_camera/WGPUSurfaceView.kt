@@ -31,6 +31,11 @@ class WGPUSurfaceView : SurfaceView, SurfaceHolder.Callback2 {
     }
 
     override fun surfaceChanged(holder: SurfaceHolder, format: Int, width: Int, height: Int) {
+        if (wgpuObj == Long.MAX_VALUE) {
+            return
+        }
+        // Surface.ROTATION_* 是 0 ~ 3，对应 0 ~ 270 度
+        rustBrige.set_device_rotation(wgpuObj, (display?.rotation ?: 0) * 90)
     }
 
     override fun surfaceCreated(holder: SurfaceHolder) {
@@ -63,7 +68,7 @@ class WGPUSurfaceView : SurfaceView, SurfaceHolder.Callback2 {
         if (wgpuObj == Long.MAX_VALUE) {
            return
         }
-        rustBrige.enter_frame(wgpuObj)
+        rustBrige.enter_frame(wgpuObj, System.nanoTime() / 1e9)
         invalidate()
     }
 
@@ -7,25 +7,40 @@
 
 import UIKit
 
-let nativeScale = Float(UIScreen.main.nativeScale)
-struct Filter {
-    public var name: String
-    public var min: Float
-    public var max: Float
-    
-    init(name: String, min: Float, max: Float) {
-        self.name = name
-        self.min = min
-        self.max = max
+struct ParamSchema: Decodable {
+    let name: String
+    let type: String
+    let min: Float
+    let max: Float
+    let `default`: Float
+    let step: Float
+}
+
+// 由 wgpu-camera 的 filter_schemas() 生成，名称与参数范围不在 UI 中硬编码
+struct Filter: Decodable {
+    let id: UInt32
+    let filter_type: String
+    let name: String
+    let input_param: String?
+    let params: [ParamSchema]
+
+    var inputParam: ParamSchema? {
+        params.first { $0.name == input_param }
     }
 }
 
-let filters = [
-    Filter.init(name: "Original", min: 0.0, max: 0.0),
-    Filter.init(name: "ASCII Art", min: 4.0 * nativeScale, max: 12.0 * nativeScale) ,
-    Filter.init(name: "Cross Hatch", min: 8.0 * nativeScale, max: 20.0 * nativeScale),
-    Filter.init(name: "Edge Detection", min: 0.05, max: 0.33)
-]
+var filters: [Filter] = []
+
+func loadFilterSchemas(canvas: OpaquePointer) {
+    guard let json = filter_schemas(canvas) else {
+        return
+    }
+    defer { free_rust_string(json) }
+    if let data = String(cString: json).data(using: .utf8),
+       let list = try? JSONDecoder().decode([Filter].self, from: data) {
+        filters = list
+    }
+}
 
 extension ViewController: UICollectionViewDelegate, UICollectionViewDataSource {
     func collectionView(_ collectionView: UICollectionView, numberOfItemsInSection section: Int) -> Int {
@@ -45,13 +60,19 @@ extension ViewController: UICollectionViewDelegate, UICollectionViewDataSource {
         }
         // call wgpu
         let filter = filters[indexPath.row]
-        slider.minimumValue = filter.min
-        slider.maximumValue = filter.max
-        slider.value = filter.min
-        minLb.text = "\(filter.min)"
-        maxLb.text = "\(filter.max)"
+        let param = filter.inputParam
+        slider.minimumValue = param?.min ?? 0.0
+        slider.maximumValue = param?.max ?? 0.0
+        slider.value = param?.default ?? 0.0
+        minLb.text = "\(slider.minimumValue)"
+        maxLb.text = "\(slider.maximumValue)"
 
-        set_filter(canvas, filter_type(UInt32(indexPath.row)), 0, filter.min)
+        if filter.filter_type == "Custom" {
+            set_custom_filter(canvas, filter.name, 0, slider.value)
+        } else {
+            set_filter_animated(canvas, filter_type(filter.id), 0.4, Crossfade)
+            change_filter_param(canvas, slider.value)
+        }
     }
     
     @IBAction func sliderValueChanged() {
@@ -61,6 +61,10 @@ class ViewController: UIViewController {
             
             let viewObj = ios_view_obj(view: viewPointer, metal_layer: metalLayer,maximum_frames: Int32(maximumFrames), callback_to_swift: callback_to_swift)
             wgpuCanvas = create_wgpu_canvas(viewObj)
+            if let canvas = wgpuCanvas {
+                loadFilterSchemas(canvas: canvas)
+                cv.reloadData()
+            }
         }
         self.displayLink.isPaused = false
         session?.start()
@@ -81,7 +85,7 @@ class ViewController: UIViewController {
             return
         }
         // call wgpu
-        enter_frame(canvas, current_tex_key)
+        enter_frame(canvas, current_tex_key, displayLink.timestamp)
         
         // 每 60 帧清理一次纹理缓存
         frameIndex += 1
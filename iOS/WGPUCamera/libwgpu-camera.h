@@ -7,6 +7,7 @@
 #ifndef libwgpu_camera_h
 #define libwgpu_camera_h 
 
+#include <stddef.h>
 #include <stdint.h>
 
 struct wgpu_canvas;
@@ -20,18 +21,100 @@ struct ios_view_obj {
 };
 
 enum filter_type {
+    Original,
     AsciiArt,
     CrossHatch,
     EdgeDetection,
+    GaussianBlur,
+    Custom,
+    Lut3D,
+    ColorAdjust,
+    ToneCurve,
+    Halftone,
+    Dither,
+};
+
+enum curve_channel {
+    Master,
+    Red,
+    Green,
+    Blue,
+};
+
+enum transition {
+    Crossfade,
+    WipeLeft,
+    WipeRight,
+    WipeUp,
+    WipeDown,
+    CircularReveal,
+    PageCurl,
+};
+
+enum compare_divider {
+    Vertical,
+    Horizontal,
+    Diagonal,
+};
+
+enum content_mode {
+    AspectFill,
+    AspectFit,
+    Stretch,
 };
 
 struct wgpu_canvas *create_wgpu_canvas(struct ios_view_obj obj);
 
 void set_filter(struct wgpu_canvas *canvas, enum filter_type ty, int opaque_background_color, float param);
+// blend from the current filter to ty over duration seconds
+void set_filter_animated(struct wgpu_canvas *canvas, enum filter_type ty, float duration, enum transition transition);
+// page curl transition to ty that follows the drag position (in surface pixels) until end_page_curl
+void begin_page_curl(struct wgpu_canvas *canvas, enum filter_type ty);
+void drag_page_curl(struct wgpu_canvas *canvas, float x, float y);
+void end_page_curl(struct wgpu_canvas *canvas, float duration);
+// original image before the divider, the active filter after it; drag moves the divider (surface pixels)
+void begin_comparison(struct wgpu_canvas *canvas, enum compare_divider divider);
+void drag_comparison(struct wgpu_canvas *canvas, float x, float y);
+void end_comparison(struct wgpu_canvas *canvas);
+// while layers are added enter_frame draws all of them in one pass, each into its normalized rectangle
+void add_layer(struct wgpu_canvas *canvas, const char *tex_key, enum filter_type ty, float x, float y, float width, float height, int z_index);
+void clear_layers(struct wgpu_canvas *canvas);
 void change_filter_param(struct wgpu_canvas *canvas,  float param);
+// grade the last stage, a Lut3D filter; return 0 if the LUT is invalid
+int set_lut_cube(struct wgpu_canvas *canvas, const char *text);
+int set_lut_hald_png(struct wgpu_canvas *canvas, const uint8_t *data, size_t len);
+// curves and levels of the last stage, a ToneCurve filter; points holds count (input, output) pairs in 0..1
+int set_tone_curve_points(struct wgpu_canvas *canvas, enum curve_channel channel, const float *points, size_t count);
+int set_levels(struct wgpu_canvas *canvas, float black_point, float white_point, float gamma);
+// palette of the last stage, a Dither filter; colors are 0xRRGGBB, a count of 0 quantizes each channel instead
+int set_dither_palette_colors(struct wgpu_canvas *canvas, const uint32_t *colors, size_t count);
+int set_dither_palette_png(struct wgpu_canvas *canvas, const uint8_t *data, size_t len);
+// JSON array of the filter schemas, release it with free_rust_string
+char *filter_schemas(struct wgpu_canvas *canvas);
+void free_rust_string(char *s);
+// the current filter chain as preset JSON, release it with free_rust_string
+char *export_preset(struct wgpu_canvas *canvas, const char *name);
+// returns 0 if the preset is invalid or uses an unregistered custom filter
+int apply_preset(struct wgpu_canvas *canvas, const char *json);
+// returns 1 on success, 0 if the shader failed to validate
+int register_filter(struct wgpu_canvas *canvas, const char *name, const char *wgsl_source);
+// returns 0 if no filter was registered with that name
+int set_custom_filter(struct wgpu_canvas *canvas, const char *name, int opaque_background_color, float param);
+void set_content_mode(struct wgpu_canvas *canvas, enum content_mode mode);
+// normalized rectangle of the source texture, a width or height of 0 removes the crop
+void set_source_crop(struct wgpu_canvas *canvas, float x, float y, float width, float height);
+// clockwise rotation of the display in degrees, the image is laid out upright for it
+void set_device_rotation(struct wgpu_canvas *canvas, float angle);
+// export: 1 mirrors the frames of capture_frame, 0 the preview
+void set_mirror(struct wgpu_canvas *canvas, int horizontal, int vertical, int export);
+// pinch-zoom, pan (surface pixels) and clockwise rotation (degrees) of the preview
+void set_view_transform(struct wgpu_canvas *canvas, float scale, float tx, float ty, float rotation);
+// maps a surface position to source texture pixels, returns 0 outside the image
+int view_to_image(struct wgpu_canvas *canvas, float x, float y, float *out_x, float *out_y);
 void set_external_texture(struct wgpu_canvas *canvas, void *raw, const char *tex_key, int width, int height);
 void remove_texture(struct wgpu_canvas *canvas, const char *tex_key);
 
-void enter_frame(struct wgpu_canvas *canvas, const char *tex_key);
+// timestamp: seconds of a monotonic clock, advances the set_filter_animated transition
+void enter_frame(struct wgpu_canvas *canvas, const char *tex_key, double timestamp);
 
 #endif /* libwgpu-camera_h */
@@ -1,9 +1,17 @@
 use app_surface::AppSurface;
-use wgpu_camera::WgpuCanvas;
-use winit::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
+use wgpu_camera::{CompareDivider, FilterType, Layer, Transition, WgpuCanvas};
+use winit::event::{ElementState, Event, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop};
 
 const TEX_KEY: &'static str = "any string";
+// 按空格键依次切换的滤镜与过渡效果
+const FILTERS: [(FilterType, Transition); 5] = [
+    (FilterType::EdgeDetection, Transition::Crossfade),
+    (FilterType::AsciiArt, Transition::WipeLeft),
+    (FilterType::GaussianBlur, Transition::CircularReveal),
+    (FilterType::CrossHatch, Transition::WipeDown),
+    (FilterType::Original, Transition::PageCurl),
+];
 
 #[cfg(not(target_arch = "wasm32"))]
 pub fn run() {
@@ -87,12 +95,18 @@ async fn create_instance() -> (EventLoop<()>, WgpuCanvas) {
 
     let app_surface = AppSurface::new(window).await;
     let mut canvas = WgpuCanvas::new(app_surface);
-    canvas.set_filter(wgpu_camera::FilterType::CrossHatch, true, 10.0);
+    canvas.set_filter(FilterType::CrossHatch, true, 10.0);
     // canvas.set_filter(wgpu_camera::FilterType::EdgeDetection, false, 0.15);
+    // cargo run --example filter --features hot-reload
+    #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+    if let Err(e) = canvas.watch_shaders(concat!(env!("CARGO_MANIFEST_DIR"), "/../wgsl")) {
+        log::error!("can't watch the wgsl directory: {}", e);
+    }
 
-    let (texture, size) = wgpu_camera::get_a_texture(&canvas.app_surface);
-    canvas.set_external_texture(
+    let (texture, size) = wgpu_camera::get_a_texture(canvas.device(), canvas.queue());
+    canvas.set_external_texture_with_format(
         texture,
+        wgpu::TextureFormat::Rgba8UnormSrgb,
         TEX_KEY.to_string(),
         (size.width as f32, size.height as f32),
     );
@@ -103,6 +117,8 @@ async fn create_instance() -> (EventLoop<()>, WgpuCanvas) {
 fn start_event_loop(event_loop: EventLoop<()>, canvas: WgpuCanvas) {
     let spawner = Spawner::new();
     let mut canvas = canvas;
+    let mut frame_index = 0_u64;
+    let mut filter_index = 0;
     event_loop.run(move |event, _, control_flow| {
         *control_flow = if cfg!(feature = "metal-auto-capture") {
             ControlFlow::Exit
@@ -114,7 +130,9 @@ fn start_event_loop(event_loop: EventLoop<()>, canvas: WgpuCanvas) {
                 #[cfg(not(target_arch = "wasm32"))]
                 spawner.run_until_stalled();
 
-                canvas.app_surface.view.request_redraw();
+                if let Some(app_surface) = canvas.app_surface() {
+                    app_surface.view.request_redraw();
+                }
             }
             Event::WindowEvent {
                 event: WindowEvent::Resized(_size),
@@ -135,10 +153,78 @@ fn start_event_loop(event_loop: EventLoop<()>, canvas: WgpuCanvas) {
                 | WindowEvent::CloseRequested => {
                     *control_flow = winit::event_loop::ControlFlow::Exit;
                 }
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            virtual_keycode: Some(VirtualKeyCode::Space),
+                            state: ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => {
+                    let (ty, transition) = FILTERS[filter_index];
+                    canvas.set_filter_animated(ty, 0.6, transition);
+                    filter_index = (filter_index + 1) % FILTERS.len();
+                }
+                // G 键切换所有滤镜的网格预览
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            virtual_keycode: Some(VirtualKeyCode::G),
+                            state: ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => {
+                    if canvas.layers().is_empty() {
+                        let filters: Vec<FilterType> = FILTERS.iter().map(|(ty, _)| *ty).collect();
+                        canvas.set_layers(Layer::grid(TEX_KEY, &filters, 3));
+                    } else {
+                        canvas.set_layers(vec![]);
+                    }
+                }
+                // C 键依次切换竖直、水平、对角线分割的对比模式与关闭
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            virtual_keycode: Some(VirtualKeyCode::C),
+                            state: ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => {
+                    let divider = match canvas.comparison() {
+                        None => Some(CompareDivider::Vertical),
+                        Some((CompareDivider::Vertical, _)) => Some(CompareDivider::Horizontal),
+                        Some((CompareDivider::Horizontal, _)) => Some(CompareDivider::Diagonal),
+                        Some((CompareDivider::Diagonal, _)) => None,
+                    };
+                    canvas.set_comparison(divider);
+                }
+                // 按住鼠标左键拖动，把当前画面像书页一样翻开
+                WindowEvent::MouseInput {
+                    state,
+                    button: MouseButton::Left,
+                    ..
+                } if canvas.comparison().is_none() => {
+                    if state == ElementState::Pressed {
+                        canvas.begin_page_curl(FILTERS[filter_index].0);
+                        filter_index = (filter_index + 1) % FILTERS.len();
+                    } else {
+                        canvas.end_page_curl(0.4);
+                    }
+                }
+                WindowEvent::CursorMoved { position, .. } => {
+                    // 对比模式中分割线跟随鼠标
+                    canvas.drag_comparison(position.x as f32, position.y as f32);
+                    canvas.drag_page_curl(position.x as f32, position.y as f32);
+                }
                 _ => {}
             },
             Event::RedrawRequested(_) => {
-                canvas.enter_frame(TEX_KEY.to_string());
+                // 按 60 fps 计算时间戳，wasm 上没有 std::time::Instant
+                frame_index += 1;
+                canvas.enter_frame(TEX_KEY.to_string(), frame_index as f64 / 60.0);
             }
             _ => (),
         }
@@ -0,0 +1,128 @@
+use app_surface::AppSurface;
+
+/// Where `WgpuCanvas` draws each frame: a window/layer surface, or an owned texture
+pub enum CanvasTarget {
+    Surface(AppSurface),
+    Offscreen(OffscreenTarget),
+}
+
+impl CanvasTarget {
+    pub fn device(&self) -> &wgpu::Device {
+        match self {
+            CanvasTarget::Surface(app_surface) => &app_surface.device,
+            CanvasTarget::Offscreen(target) => &target.device,
+        }
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        match self {
+            CanvasTarget::Surface(app_surface) => &app_surface.queue,
+            CanvasTarget::Offscreen(target) => &target.queue,
+        }
+    }
+
+    pub fn config(&self) -> &wgpu::SurfaceConfiguration {
+        match self {
+            CanvasTarget::Surface(app_surface) => &app_surface.config,
+            CanvasTarget::Offscreen(target) => &target.config,
+        }
+    }
+
+    pub fn scale_factor(&self) -> f32 {
+        match self {
+            CanvasTarget::Surface(app_surface) => app_surface.scale_factor,
+            CanvasTarget::Offscreen(target) => target.scale_factor,
+        }
+    }
+}
+
+/// Headless render target, used on CI machines and servers that have no window
+pub struct OffscreenTarget {
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    // 没有 surface，但保留一份配置，让尺寸与格式的读取方式和 AppSurface 一致
+    pub config: wgpu::SurfaceConfiguration,
+    pub texture: wgpu::Texture,
+    pub scale_factor: f32,
+}
+
+impl OffscreenTarget {
+    pub fn new(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        size: (u32, u32),
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::TEXTURE_BINDING,
+            format,
+            width: size.0.max(1),
+            height: size.1.max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+        };
+        let texture = Self::create_texture(&device, &config);
+        Self {
+            device,
+            queue,
+            config,
+            texture,
+            scale_factor: 1.0,
+        }
+    }
+
+    /// Request a device from the fallback (software) adapter, or from any adapter if the
+    /// platform does not provide one
+    pub async fn request(size: (u32, u32), format: wgpu::TextureFormat) -> Option<Self> {
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let mut adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::LowPower,
+                force_fallback_adapter: true,
+                compatible_surface: None,
+            })
+            .await;
+        if adapter.is_none() {
+            adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions::default())
+                .await;
+        }
+        let adapter = adapter?;
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("offscreen device"),
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits()),
+                },
+                None,
+            )
+            .await
+            .ok()?;
+        Some(Self::new(device, queue, size, format))
+    }
+
+    pub fn resize(&mut self, size: (u32, u32)) {
+        self.config.width = size.0.max(1);
+        self.config.height = size.1.max(1);
+        self.texture = Self::create_texture(&self.device, &self.config);
+    }
+
+    fn create_texture(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("offscreen target"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: config.usage,
+        })
+    }
+}
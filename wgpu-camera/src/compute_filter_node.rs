@@ -1,121 +1,244 @@
+use crate::compute_node::{ComputeBindings, ComputeNode, ComputeNodeBuilder};
 use crate::display_node::DisplayNode;
-use app_surface::AppSurface;
+use crate::shader_manager::ShaderError;
+use crate::FilterParams;
 use idroid::vertex::PosTex;
 use std::collections::HashMap;
-use wgpu::{BindGroupLayout, Buffer, ShaderModule, Texture};
+use wgpu::{Buffer, ShaderModule, StorageTextureAccess, TextureFormat};
 
+const WORKGROUP_SIZE: (u32, u32) = (16, 16);
+
+/// How a compute pass is dispatched, see `FilterParams::compute_dispatches`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PassDispatch {
+    /// One invocation per pixel
+    PerPixel,
+    /// A single workgroup that walks the whole image by itself
+    SingleWorkgroup,
+    /// The pass is not run and the output of the previous pass is displayed, only the last
+    /// passes can be skipped
+    Skip,
+}
+
+struct ComputeOutput {
+    _texture: wgpu::Texture,
+    view: wgpu::TextureView,
+}
+
+// 每个 tex_key 对应的计算资源：每个 pass 一张输出纹理及其 bind group
+struct ComputeResources {
+    size: (u32, u32),
+    outputs: Vec<ComputeOutput>,
+    // 所有 pass 共用的读写 storage buffer，见 FilterType::compute_scratch_rows
+    scratch: Option<wgpu::Buffer>,
+    bind_groups: Vec<wgpu::BindGroup>,
+    // 每个 pass 的输出各有一个，跳过后面的 pass 时显示前一个 pass 的输出
+    display_bind_groups: Vec<wgpu::BindGroup>,
+}
+
+/// 由一个或多个 compute pass 组成的滤镜
+///
+/// 每个 pass 读取上一个 pass 的输出（第一个 pass 读取外部纹理），写入一张与输入同尺寸的 storage texture，
+/// 最后一个 pass 的输出再通过 DisplayNode 绘制出来。
 pub(crate) struct ComputeFilterNode {
-    bind_group_layout: BindGroupLayout,
-    pipeline: wgpu::ComputePipeline,
-    bind_groups: HashMap<String, wgpu::BindGroup>,
+    passes: Vec<ComputeNode>,
+    dispatches: Vec<PassDispatch>,
+    resources: HashMap<String, ComputeResources>,
     display_node: DisplayNode,
+    scratch_rows: Option<u32>,
 }
 
 impl ComputeFilterNode {
     pub fn new(
-        app_surface: &AppSurface,
-        compute_shader: &ShaderModule,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        viewport_size: (f32, f32),
+        compute_shaders: &[&ShaderModule],
         display_shader: &ShaderModule,
+        scratch_rows: Option<u32>,
     ) -> Self {
-        let device = &app_surface.device;
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: None,
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    count: None,
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: true,
-                        min_binding_size: wgpu::BufferSize::new(0),
-                    },
-                },
-                wgpu::BindGroupLayoutEntry {
-                    count: None,
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::StorageTexture {
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        access: wgpu::StorageTextureAccess::ReadWrite,
-                        format: wgpu::TextureFormat::Rgba8Unorm,
-                    },
-                },
-                wgpu::BindGroupLayoutEntry {
-                    count: None,
-                    binding: 2,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::StorageTexture {
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        access: wgpu::StorageTextureAccess::ReadWrite,
-                        format: wgpu::TextureFormat::Rgba8Unorm,
-                    },
-                },
-            ],
-        });
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: None,
-            push_constant_ranges: &[],
-            bind_group_layouts: &[&bind_group_layout],
-        });
-
-        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            layout: Some(&pipeline_layout),
-            module: compute_shader,
-            entry_point: "cs_main",
-            label: None,
-        });
-        let display_node = DisplayNode::new::<PosTex>(app_surface, display_shader);
-        // display_node.bi
+        let passes: Vec<ComputeNode> = compute_shaders
+            .iter()
+            .map(|shader| Self::pass_builder(shader, scratch_rows).build(device))
+            .collect();
+        let dispatches = vec![PassDispatch::PerPixel; passes.len()];
+        let display_node =
+            DisplayNode::new::<PosTex>(device, format, viewport_size, display_shader, None);
         Self {
-            bind_group_layout,
-            pipeline,
-            bind_groups: HashMap::new(),
+            passes,
+            dispatches,
+            resources: HashMap::new(),
             display_node,
+            scratch_rows,
+        }
+    }
+
+    /// Check that `shader` fits the pipeline layout of a pass, see `ShaderManager::reload_builtin`
+    #[cfg(all(
+        feature = "hot-reload",
+        not(any(target_os = "ios", target_os = "android", target_arch = "wasm32"))
+    ))]
+    pub fn check_pass(
+        device: &wgpu::Device,
+        shader: &ShaderModule,
+        scratch_rows: Option<u32>,
+    ) -> Result<(), ShaderError> {
+        crate::shader_manager::catch_validation_error(device, || {
+            Self::pass_builder(shader, scratch_rows).build(device);
+        })
+    }
+
+    // 资源要等到绑定外部纹理时才创建，管线只需要各个绑定的类型
+    fn pass_builder(shader: &ShaderModule, scratch_rows: Option<u32>) -> ComputeNodeBuilder<'_> {
+        let mut storage_buffers = vec![true];
+        if scratch_rows.is_some() {
+            storage_buffers.push(false);
+        }
+        ComputeNodeBuilder::new(shader)
+            .with_storage_buffers(storage_buffers)
+            .with_storage_textures(vec![(
+                StorageTextureAccess::WriteOnly,
+                TextureFormat::Rgba8Unorm,
+            )])
+            .with_sampled_textures(1)
+            .with_workgroup_size(WORKGROUP_SIZE)
+            .with_label("compute filter")
+    }
+
+    // 最后一个会执行的 pass 的输出
+    fn display_index(&self) -> usize {
+        self.dispatches
+            .iter()
+            .rposition(|dispatch| *dispatch != PassDispatch::Skip)
+            .unwrap_or(0)
+    }
+
+    // 每个元素是一个 vec4<f32>
+    fn create_scratch(device: &wgpu::Device, width: u32, rows: u32) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("compute filter scratch"),
+            size: width.max(1) as u64 * rows.max(1) as u64 * 16,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_output(device: &wgpu::Device, size: (u32, u32)) -> ComputeOutput {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("compute filter output"),
+            size: wgpu::Extent3d {
+                width: size.0.max(1),
+                height: size.1.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        ComputeOutput {
+            _texture: texture,
+            view,
         }
     }
 }
 
 impl crate::FilterNode for ComputeFilterNode {
-    fn change_filter(&mut self, _app_surface: &AppSurface, _shader_module: &wgpu::ShaderModule) {}
+    fn change_filter(
+        &mut self,
+        _device: &wgpu::Device,
+        _format: wgpu::TextureFormat,
+        _shader_module: &wgpu::ShaderModule,
+    ) -> Result<(), ShaderError> {
+        Ok(())
+    }
 
     fn update_viewport(&mut self, viewport: (f32, f32, f32, f32)) {
         self.display_node.viewport = viewport;
     }
 
+    fn update_params(&mut self, params: &FilterParams) {
+        self.dispatches = params
+            .compute_dispatches()
+            .unwrap_or_else(|| vec![PassDispatch::PerPixel; self.passes.len()]);
+    }
+
     fn update_bind_group(
         &mut self,
-        app_surface: &AppSurface,
-        _mvp_buffer: &Buffer,
+        device: &wgpu::Device,
+        mvp_buffer: &Buffer,
         params_buffer: &Buffer,
-        external_texture: &Texture,
-        external_tv: Option<wgpu::TextureView>,
+        texture_view: &wgpu::TextureView,
+        size: (u32, u32),
         tex_key: String,
     ) {
-        let texture_view = match external_tv {
-            Some(tv) => tv,
-            None => external_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+        // 输出纹理与 scratch buffer 只在尺寸变化时重新创建
+        let (outputs, scratch) = match self.resources.remove(&tex_key) {
+            Some(res) if res.size == size => (res.outputs, res.scratch),
+            _ => (
+                (0..self.passes.len())
+                    .map(|_| Self::create_output(device, size))
+                    .collect(),
+                self.scratch_rows
+                    .map(|rows| Self::create_scratch(device, size.0, rows)),
+            ),
         };
-        let bind_group = app_surface
-            .device
-            .create_bind_group(&wgpu::BindGroupDescriptor {
-                layout: &self.bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: params_buffer.as_entire_binding(),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::TextureView(&texture_view),
-                    },
-                ],
-                label: None,
-            });
-        self.bind_groups.insert(tex_key, bind_group);
+        let bind_groups = self
+            .passes
+            .iter()
+            .enumerate()
+            .map(|(i, pass)| {
+                let input_view = if i == 0 {
+                    texture_view
+                } else {
+                    &outputs[i - 1].view
+                };
+                let mut storage_buffers = vec![(params_buffer, true)];
+                if let Some(scratch) = scratch.as_ref() {
+                    storage_buffers.push((scratch, false));
+                }
+                let bindings = ComputeBindings {
+                    storage_buffers,
+                    storage_textures: vec![(
+                        &outputs[i].view,
+                        StorageTextureAccess::WriteOnly,
+                        TextureFormat::Rgba8Unorm,
+                    )],
+                    sampled_textures: vec![input_view],
+                    ..Default::default()
+                };
+                pass.create_bind_group(device, &bindings)
+            })
+            .collect();
+        let display_views: Vec<&wgpu::TextureView> = if outputs.is_empty() {
+            vec![texture_view]
+        } else {
+            outputs.iter().map(|output| &output.view).collect()
+        };
+        let display_bind_groups = display_views
+            .into_iter()
+            .map(|view| {
+                self.display_node
+                    .create_bind_group(device, mvp_buffer, params_buffer, view, None)
+            })
+            .collect();
+        self.resources.insert(
+            tex_key,
+            ComputeResources {
+                size,
+                outputs,
+                scratch,
+                bind_groups,
+                display_bind_groups,
+            },
+        );
     }
 
-    fn remove_bind_group(&mut self, _tex_key: String) {}
+    fn remove_bind_group(&mut self, tex_key: String) {
+        self.resources.remove(&tex_key);
+    }
 
     fn enter_frame(
         &mut self,
@@ -123,11 +246,57 @@ impl crate::FilterNode for ComputeFilterNode {
         encoder: &mut wgpu::CommandEncoder,
         tex_key: String,
     ) {
-        let bind_group = self.bind_groups.get(&tex_key);
-        if bind_group.is_none() {
-            return;
+        self.prepare(encoder, &tex_key);
+        let index = self.display_index();
+        if let Some(bind_group) = self
+            .resources
+            .get(&tex_key)
+            .and_then(|res| res.display_bind_groups.get(index))
+        {
+            self.display_node
+                .begin_render_pass(frame_view, encoder, Some(bind_group))
+        }
+    }
+
+    fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder, tex_key: &str) {
+        let res = match self.resources.get(tex_key) {
+            Some(res) => res,
+            None => return,
+        };
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("compute filter"),
+        });
+        let extent = wgpu::Extent3d {
+            width: res.size.0,
+            height: res.size.1,
+            depth_or_array_layers: 1,
+        };
+        // 1x1 的尺寸正好对应一个 workgroup
+        let single = wgpu::Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
+        };
+        let passes = self.passes.iter().zip(res.bind_groups.iter());
+        for ((pass, bind_group), dispatch) in passes.zip(self.dispatches.iter()) {
+            match dispatch {
+                PassDispatch::PerPixel => pass.dispatch_by_pass(&mut cpass, bind_group, extent),
+                PassDispatch::SingleWorkgroup => {
+                    pass.dispatch_by_pass(&mut cpass, bind_group, single)
+                }
+                PassDispatch::Skip => {}
+            }
+        }
+    }
+
+    fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>, tex_key: &str) {
+        let index = self.display_index();
+        if let Some(bind_group) = self
+            .resources
+            .get(tex_key)
+            .and_then(|res| res.display_bind_groups.get(index))
+        {
+            self.display_node.draw(rpass, bind_group);
         }
-        self.display_node
-            .begin_render_pass(frame_view, encoder, None)
     }
 }
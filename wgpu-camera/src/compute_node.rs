@@ -1,23 +1,33 @@
-use wgpu::util::DeviceExt;
-use wgpu::{
-    BindingType, Buffer, BufferBindingType, PipelineLayout, ShaderModule, ShaderStages, Texture,
-    TextureFormat,
-};
+use wgpu::{Buffer, ShaderModule, ShaderStages, StorageTextureAccess, TextureFormat, TextureView};
 
-pub struct ComputeNode {
-    pub bind_group_layout: wgpu::BindGroupLayout,
-    pub bind_group: Option<wgpu::BindGroup>,
-    pub pipeline_layout: wgpu::PipelineLayout,
-    pub pipeline: wgpu::ComputePipeline,
-    pub workgroup_size: (u32, u32),
+/// Resources of a compute bind group
+///
+/// Binding indices follow the declaration order: uniform buffers, storage buffers,
+/// storage textures and then sampled textures.
+#[derive(Default)]
+pub struct ComputeBindings<'a> {
+    pub uniform_buffers: Vec<&'a Buffer>,
+    // (buffer, read_only)
+    pub storage_buffers: Vec<(&'a Buffer, bool)>,
+    pub storage_textures: Vec<(&'a TextureView, StorageTextureAccess, TextureFormat)>,
+    pub sampled_textures: Vec<&'a TextureView>,
 }
 
-impl ComputeNode {
-    pub fn new(uniforms: u32, storages: u32, storage_textures: u32, sampler_textures: u32) -> Self {
+/// Kinds of the resources in a compute bind group, in the same order as `ComputeBindings`
+#[derive(Clone, Debug, Default)]
+pub struct ComputeLayout {
+    pub uniform_buffers: usize,
+    // read_only
+    pub storage_buffers: Vec<bool>,
+    pub storage_textures: Vec<(StorageTextureAccess, TextureFormat)>,
+    pub sampled_textures: usize,
+}
+
+impl ComputeLayout {
+    fn layout_entries(&self) -> Vec<wgpu::BindGroupLayoutEntry> {
         let mut layouts: Vec<wgpu::BindGroupLayoutEntry> = vec![];
         let mut b_index = 0_u32;
-        for i in 0..uniforms.len() {
-            let buffer_obj = uniforms[i];
+        for _ in 0..self.uniform_buffers {
             layouts.push(wgpu::BindGroupLayoutEntry {
                 binding: b_index,
                 visibility: ShaderStages::COMPUTE,
@@ -31,12 +41,14 @@ impl ComputeNode {
             b_index += 1;
         }
 
-        for i in 0..storages.len() {
+        for read_only in self.storage_buffers.iter() {
             layouts.push(wgpu::BindGroupLayoutEntry {
                 binding: b_index,
                 visibility: ShaderStages::COMPUTE,
                 ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    ty: wgpu::BufferBindingType::Storage {
+                        read_only: *read_only,
+                    },
                     has_dynamic_offset: false,
                     min_binding_size: wgpu::BufferSize::new(0),
                 },
@@ -46,20 +58,20 @@ impl ComputeNode {
         }
         let view_dimension = wgpu::TextureViewDimension::D2;
 
-        for i in 0..storage_textures.len() {
+        for (access, format) in self.storage_textures.iter() {
             layouts.push(wgpu::BindGroupLayoutEntry {
                 binding: b_index,
                 visibility: ShaderStages::COMPUTE,
                 ty: wgpu::BindingType::StorageTexture {
                     view_dimension,
-                    access: wgpu::StorageTextureAccess::Write,
-                    format: any_tex.format,
+                    access: *access,
+                    format: *format,
                 },
                 count: None,
             });
             b_index += 1;
         }
-        for i in 0..sampler_textures.len() {
+        for _ in 0..self.sampled_textures {
             layouts.push(wgpu::BindGroupLayoutEntry {
                 binding: b_index,
                 visibility: ShaderStages::COMPUTE,
@@ -72,5 +84,197 @@ impl ComputeNode {
             });
             b_index += 1;
         }
+        layouts
+    }
+}
+
+impl<'a> ComputeBindings<'a> {
+    /// The layout that these resources fit
+    pub fn layout(&self) -> ComputeLayout {
+        ComputeLayout {
+            uniform_buffers: self.uniform_buffers.len(),
+            storage_buffers: self.storage_buffers.iter().map(|(_, r)| *r).collect(),
+            storage_textures: self
+                .storage_textures
+                .iter()
+                .map(|(_, access, format)| (*access, *format))
+                .collect(),
+            sampled_textures: self.sampled_textures.len(),
+        }
+    }
+
+    fn bind_group_entries(&self) -> Vec<wgpu::BindGroupEntry<'a>> {
+        let mut entries: Vec<wgpu::BindGroupEntry> = vec![];
+        for buffer in self.uniform_buffers.iter() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: entries.len() as u32,
+                resource: buffer.as_entire_binding(),
+            });
+        }
+        for (buffer, _) in self.storage_buffers.iter() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: entries.len() as u32,
+                resource: buffer.as_entire_binding(),
+            });
+        }
+        for (view, _, _) in self.storage_textures.iter() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: entries.len() as u32,
+                resource: wgpu::BindingResource::TextureView(view),
+            });
+        }
+        for view in self.sampled_textures.iter() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: entries.len() as u32,
+                resource: wgpu::BindingResource::TextureView(view),
+            });
+        }
+        entries
+    }
+}
+
+/// Derives the bind group layout and the pipeline of a `ComputeNode` from the kinds of its
+/// resources, the resources themselves are bound later by `ComputeNode::create_bind_group`
+pub struct ComputeNodeBuilder<'a> {
+    shader_module: &'a ShaderModule,
+    entry_point: &'a str,
+    layout: ComputeLayout,
+    workgroup_size: (u32, u32),
+    label: Option<&'a str>,
+}
+
+impl<'a> ComputeNodeBuilder<'a> {
+    pub fn new(shader_module: &'a ShaderModule) -> Self {
+        Self {
+            shader_module,
+            entry_point: "cs_main",
+            layout: ComputeLayout::default(),
+            workgroup_size: (16, 16),
+            label: None,
+        }
+    }
+
+    pub fn with_entry_point(mut self, entry_point: &'a str) -> Self {
+        self.entry_point = entry_point;
+        self
+    }
+
+    pub fn with_uniform_buffers(mut self, count: usize) -> Self {
+        self.layout.uniform_buffers = count;
+        self
+    }
+
+    /// `read_only` flag of each storage buffer
+    pub fn with_storage_buffers(mut self, buffers: Vec<bool>) -> Self {
+        self.layout.storage_buffers = buffers;
+        self
+    }
+
+    pub fn with_storage_textures(
+        mut self,
+        textures: Vec<(StorageTextureAccess, TextureFormat)>,
+    ) -> Self {
+        self.layout.storage_textures = textures;
+        self
+    }
+
+    pub fn with_sampled_textures(mut self, count: usize) -> Self {
+        self.layout.sampled_textures = count;
+        self
+    }
+
+    /// Replace the whole layout, e.g. with `ComputeBindings::layout`
+    pub fn with_layout(mut self, layout: ComputeLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Must match the `@workgroup_size` declared by the shader
+    pub fn with_workgroup_size(mut self, workgroup_size: (u32, u32)) -> Self {
+        self.workgroup_size = workgroup_size;
+        self
+    }
+
+    pub fn with_label(mut self, label: &'a str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    pub fn build(self, device: &wgpu::Device) -> ComputeNode {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: self.label,
+            entries: &self.layout.layout_entries(),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: self.label,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: self.label,
+            layout: Some(&pipeline_layout),
+            module: self.shader_module,
+            entry_point: self.entry_point,
+        });
+
+        ComputeNode {
+            bind_group_layout,
+            pipeline_layout,
+            pipeline,
+            workgroup_size: self.workgroup_size,
+        }
+    }
+}
+
+pub struct ComputeNode {
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub pipeline_layout: wgpu::PipelineLayout,
+    pub pipeline: wgpu::ComputePipeline,
+    pub workgroup_size: (u32, u32),
+}
+
+impl ComputeNode {
+    /// Bind resources of the kinds the node was built with
+    pub fn create_bind_group(
+        &self,
+        device: &wgpu::Device,
+        bindings: &ComputeBindings,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.bind_group_layout,
+            entries: &bindings.bind_group_entries(),
+        })
+    }
+
+    /// Workgroup count that covers `extent`
+    pub fn workgroup_count(&self, extent: wgpu::Extent3d) -> (u32, u32, u32) {
+        (
+            (extent.width + self.workgroup_size.0 - 1) / self.workgroup_size.0,
+            (extent.height + self.workgroup_size.1 - 1) / self.workgroup_size.1,
+            extent.depth_or_array_layers.max(1),
+        )
+    }
+
+    pub fn dispatch(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        bind_group: &wgpu::BindGroup,
+        extent: wgpu::Extent3d,
+    ) {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+        self.dispatch_by_pass(&mut cpass, bind_group, extent);
+    }
+
+    pub fn dispatch_by_pass<'a>(
+        &'a self,
+        cpass: &mut wgpu::ComputePass<'a>,
+        bind_group: &'a wgpu::BindGroup,
+        extent: wgpu::Extent3d,
+    ) {
+        let (x, y, z) = self.workgroup_count(extent);
+        cpass.set_pipeline(&self.pipeline);
+        cpass.set_bind_group(0, bind_group, &[]);
+        cpass.dispatch_workgroups(x, y, z);
     }
 }
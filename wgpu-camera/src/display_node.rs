@@ -1,6 +1,7 @@
-use app_surface::AppSurface;
+use crate::shader_manager::{catch_validation_error, ShaderError};
 use bytemuck::Pod;
 use idroid::{geometry::Plane, vertex::Vertex, BufferObj};
+use std::rc::Rc;
 use wgpu::util::DeviceExt;
 use wgpu::{
     BindingType, Buffer, BufferBindingType, PipelineLayout, ShaderModule, ShaderStages, Texture,
@@ -9,6 +10,7 @@ use wgpu::{
 
 pub struct DisplayNode {
     sampler: wgpu::Sampler,
+    external_sampler: Option<Rc<wgpu::Sampler>>,
     vertex_buf: BufferObj,
     index_buf: wgpu::Buffer,
     index_count: usize,
@@ -23,11 +25,16 @@ pub struct DisplayNode {
 
 #[allow(dead_code)]
 impl DisplayNode {
-    pub fn new<T: Vertex + Pod>(app_surface: &AppSurface, shader_module: &ShaderModule) -> Self {
-        let device = &app_surface.device;
-        let corlor_format = app_surface.config.format;
-
-        let sampler = app_surface.device.create_sampler(&wgpu::SamplerDescriptor {
+    /// `aux_texture` adds a texture of that dimension at binding 4 and a linear sampler for it at
+    /// binding 5, e.g. the 3D texture of `FilterType::Lut3D`
+    pub fn new<T: Vertex + Pod>(
+        device: &wgpu::Device,
+        corlor_format: TextureFormat,
+        viewport_size: (f32, f32),
+        shader_module: &ShaderModule,
+        aux_texture: Option<wgpu::TextureViewDimension>,
+    ) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             label: None,
             address_mode_u: wgpu::AddressMode::ClampToEdge,
             address_mode_v: wgpu::AddressMode::ClampToEdge,
@@ -38,45 +45,9 @@ impl DisplayNode {
             ..Default::default()
         });
 
+        let layout_entries = Self::layout_entries(aux_texture);
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: ShaderStages::VERTEX,
-                    ty: BindingType::Buffer {
-                        ty: BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: wgpu::BufferSize::new(0),
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: ShaderStages::FRAGMENT,
-                    ty: BindingType::Buffer {
-                        ty: BufferBindingType::Storage { read_only: true },
-                        has_dynamic_offset: false,
-                        min_binding_size: wgpu::BufferSize::new(0),
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 2,
-                    visibility: ShaderStages::FRAGMENT,
-                    ty: BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 3,
-                    visibility: ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None,
-                },
-            ],
+            entries: &layout_entries,
             label: None,
         });
 
@@ -114,6 +85,7 @@ impl DisplayNode {
 
         DisplayNode {
             sampler,
+            external_sampler: None,
             vertex_buf,
             index_buf,
             index_count: index_data.len(),
@@ -123,61 +95,173 @@ impl DisplayNode {
             vertex_attributes,
             pipeline_layout,
             pipeline,
-            viewport: (
-                0.0,
-                0.0,
-                app_surface.config.width as f32,
-                app_surface.config.height as f32,
-            ),
+            viewport: (0.0, 0.0, viewport_size.0, viewport_size.1),
         }
     }
 
+    /// Check that `shader_module` fits the bind group layout and color target of a `DisplayNode`
+    /// by building a throwaway pipeline, so that a shader with other bindings or outputs is
+    /// reported as an error instead of reaching wgpu's uncaptured error handler
+    pub fn check_shader<T: Vertex + Pod>(
+        device: &wgpu::Device,
+        corlor_format: TextureFormat,
+        shader_module: &ShaderModule,
+        aux_texture: Option<wgpu::TextureViewDimension>,
+    ) -> Result<(), ShaderError> {
+        catch_validation_error(device, || {
+            let bind_group_layout =
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    entries: &Self::layout_entries(aux_texture),
+                    label: None,
+                });
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+            Self::create_pipeline(
+                device,
+                corlor_format,
+                &pipeline_layout,
+                std::mem::size_of::<T>() as wgpu::BufferAddress,
+                &T::vertex_attributes(0),
+                shader_module,
+            );
+        })
+    }
+
+    fn layout_entries(
+        aux_texture: Option<wgpu::TextureViewDimension>,
+    ) -> Vec<wgpu::BindGroupLayoutEntry> {
+        let mut layout_entries = vec![
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(0),
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(0),
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ];
+        if let Some(view_dimension) = aux_texture {
+            layout_entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 4,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension,
+                    multisampled: false,
+                },
+                count: None,
+            });
+            // binding 3 可能是外部的 YCbCr sampler，辅助纹理使用自己的 sampler
+            layout_entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 5,
+                visibility: ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            });
+        }
+        layout_entries
+    }
+
     pub fn create_bind_group(
         &self,
-        app_surface: &AppSurface,
+        device: &wgpu::Device,
         mvp_buffer: &Buffer,
         params_buffer: &Buffer,
         texture_view: &wgpu::TextureView,
+        aux_view: Option<&wgpu::TextureView>,
     ) -> wgpu::BindGroup {
-        app_surface
-            .device
-            .create_bind_group(&wgpu::BindGroupDescriptor {
-                layout: &self.bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: mvp_buffer.as_entire_binding(),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: params_buffer.as_entire_binding(),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 2,
-                        resource: wgpu::BindingResource::TextureView(texture_view),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 3,
-                        resource: wgpu::BindingResource::Sampler(&self.sampler),
-                    },
-                ],
-                label: None,
-            })
+        let sampler = match &self.external_sampler {
+            Some(sampler) => sampler,
+            None => &self.sampler,
+        };
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: mvp_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: params_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(texture_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ];
+        if let Some(aux_view) = aux_view {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(aux_view),
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::Sampler(&self.sampler),
+            });
+        }
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &entries,
+            label: None,
+        })
     }
 
-    pub fn change_filter(&mut self, app_surface: &AppSurface, shader_module: &ShaderModule) {
-        self.pipeline = Self::create_pipeline(
-            &app_surface.device,
-            app_surface.config.format,
-            &self.pipeline_layout,
-            self.array_stride,
-            &self.vertex_attributes,
-            shader_module,
-        );
+    /// Replace the pipeline, the current one is kept if wgpu rejects `shader_module`
+    pub fn change_filter(
+        &mut self,
+        device: &wgpu::Device,
+        corlor_format: TextureFormat,
+        shader_module: &ShaderModule,
+    ) -> Result<(), ShaderError> {
+        self.pipeline = catch_validation_error(device, || {
+            Self::create_pipeline(
+                device,
+                corlor_format,
+                &self.pipeline_layout,
+                self.array_stride,
+                &self.vertex_attributes,
+                shader_module,
+            )
+        })?;
+        Ok(())
     }
 
-    pub fn update_sampler(&mut self, sampler: wgpu::Sampler) {
-        self.sampler = sampler;
+    pub fn update_sampler(&mut self, sampler: Option<Rc<wgpu::Sampler>>) {
+        self.external_sampler = sampler;
     }
 
     pub fn begin_render_pass(
@@ -202,8 +286,13 @@ impl DisplayNode {
             })],
             depth_stencil_attachment: None,
         });
+        self.draw(&mut rpass, bg);
+    }
+
+    /// Draw into a render pass that is shared with other nodes
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>, bind_group: &'a wgpu::BindGroup) {
         rpass.set_pipeline(&self.pipeline);
-        rpass.set_bind_group(0, bg, &[]);
+        rpass.set_bind_group(0, bind_group, &[]);
         rpass.set_index_buffer(self.index_buf.slice(..), wgpu::IndexFormat::Uint32);
         rpass.set_vertex_buffer(0, self.vertex_buf.buffer.slice(..));
         rpass.set_viewport(
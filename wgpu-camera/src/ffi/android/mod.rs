@@ -1,10 +1,11 @@
 use crate::wgpu_canvas::WgpuCanvas;
+use crate::Preset;
 use android_logger::Config;
 use app_surface::AppSurface;
 use ash::vk;
 use hal::api::Vulkan;
-use jni::objects::JClass;
-use jni::sys::{jlong, jobject};
+use jni::objects::{JClass, JString};
+use jni::sys::{jboolean, jdouble, jint, jlong, jobject, jstring, JNI_FALSE, JNI_TRUE};
 use jni::JNIEnv;
 use jni_fn::jni_fn;
 use log::{info, Level};
@@ -46,6 +47,7 @@ pub fn create_cameracanvas(env: *mut JNIEnv, _: JClass, surface: jobject) -> jlo
 
     let mut canvas = WgpuCanvas::new(AppSurface::new(env as *mut _, surface));
     info!("WgpuCanvas created!");
+    let app_surface = canvas.app_surface().unwrap();
 
     // let device_desc = wgpu::DeviceDescriptor::default();
     // let instance = util::create_instance();
@@ -53,14 +55,13 @@ pub fn create_cameracanvas(env: *mut JNIEnv, _: JClass, surface: jobject) -> jlo
 
     // Load vkGetAndroidHardwareBufferXXX functions.
     let (ahb_fn, pd_mem_properties) = unsafe {
-        let raw_instance = canvas
-            .app_surface
+        let raw_instance = app_surface
             .instance
             .as_hal::<Vulkan>()
             .unwrap()
             .shared_instance()
             .raw_instance();
-        canvas.app_surface.device.as_hal::<Vulkan, _, _>(|device| {
+        app_surface.device.as_hal::<Vulkan, _, _>(|device| {
             let handle = device.unwrap().raw_device().handle();
             let load_fn = |name: &std::ffi::CStr| {
                 std::mem::transmute(raw_instance.get_device_proc_addr(handle, name.as_ptr()))
@@ -74,8 +75,8 @@ pub fn create_cameracanvas(env: *mut JNIEnv, _: JClass, surface: jobject) -> jlo
 
     let (camera, session_output) = unsafe {
         let output = SessionOutput::new(
-            canvas.app_surface.config.width as i32,
-            canvas.app_surface.config.height as i32,
+            app_surface.config.width as i32,
+            app_surface.config.height as i32,
         );
         let camera = CameraManager::new(&output.native_window);
         (camera, output)
@@ -122,7 +123,7 @@ pub fn stop_capturing(_env: *mut JNIEnv, _: JClass, obj: jlong) {
 
 #[no_mangle]
 #[jni_fn("name.jinleili.wgpu_camera.RustBridge")]
-pub fn enter_frame(_env: *mut JNIEnv, _: JClass, obj: jlong) {
+pub fn enter_frame(_env: *mut JNIEnv, _: JClass, obj: jlong, timestamp: jdouble) {
     let mut wgpu_obj = unsafe { &mut *(obj as *mut AndroidCamera) };
     unsafe {
         let ahw_buffer = wgpu_obj.session_output.get_latest_buffer();
@@ -139,7 +140,79 @@ pub fn enter_frame(_env: *mut JNIEnv, _: JClass, obj: jlong) {
             );
         }
     }
-    wgpu_obj.canvas.enter_frame(TEX_KEY.to_string());
+    wgpu_obj.canvas.enter_frame(TEX_KEY.to_string(), timestamp);
+}
+
+/// JSON array of the filter schemas
+#[no_mangle]
+#[jni_fn("name.jinleili.wgpu_camera.RustBridge")]
+pub fn filter_schemas(env: JNIEnv, _: JClass, obj: jlong) -> jstring {
+    let wgpu_obj = unsafe { &mut *(obj as *mut AndroidCamera) };
+    let json = serde_json::to_string(&wgpu_obj.canvas.filter_schemas()).unwrap_or_default();
+    env.new_string(json)
+        .map(|s| s.into_inner())
+        .unwrap_or(std::ptr::null_mut())
+}
+
+/// The current filter chain as preset JSON
+#[no_mangle]
+#[jni_fn("name.jinleili.wgpu_camera.RustBridge")]
+pub fn export_preset(env: JNIEnv, _: JClass, obj: jlong, name: JString) -> jstring {
+    let wgpu_obj = unsafe { &mut *(obj as *mut AndroidCamera) };
+    let name: String = env.get_string(name).map(|s| s.into()).unwrap_or_default();
+    let json = wgpu_obj
+        .canvas
+        .export_preset(&name)
+        .to_json()
+        .unwrap_or_default();
+    env.new_string(json)
+        .map(|s| s.into_inner())
+        .unwrap_or(std::ptr::null_mut())
+}
+
+#[no_mangle]
+#[jni_fn("name.jinleili.wgpu_camera.RustBridge")]
+pub fn apply_preset(env: JNIEnv, _: JClass, obj: jlong, json: JString) -> jboolean {
+    let wgpu_obj = unsafe { &mut *(obj as *mut AndroidCamera) };
+    let json: String = match env.get_string(json) {
+        Ok(json) => json.into(),
+        Err(_) => return JNI_FALSE,
+    };
+    match Preset::from_json(&json).and_then(|preset| wgpu_obj.canvas.apply_preset(&preset)) {
+        Ok(_) => JNI_TRUE,
+        Err(e) => {
+            log::error!("apply_preset: {}", e);
+            JNI_FALSE
+        }
+    }
+}
+
+/// `rotation`: `Display.getRotation()` in degrees
+#[no_mangle]
+#[jni_fn("name.jinleili.wgpu_camera.RustBridge")]
+pub fn set_device_rotation(_env: *mut JNIEnv, _: JClass, obj: jlong, rotation: jint) {
+    let wgpu_obj = unsafe { &mut *(obj as *mut AndroidCamera) };
+    wgpu_obj.canvas.set_device_rotation(rotation as f32);
+}
+
+/// `export` selects the mirroring of captured frames instead of the preview
+#[no_mangle]
+#[jni_fn("name.jinleili.wgpu_camera.RustBridge")]
+pub fn set_mirror(
+    _env: *mut JNIEnv,
+    _: JClass,
+    obj: jlong,
+    horizontal: jboolean,
+    vertical: jboolean,
+    export: jboolean,
+) {
+    let wgpu_obj = unsafe { &mut *(obj as *mut AndroidCamera) };
+    let mirror = crate::Mirror::new(horizontal == JNI_TRUE, vertical == JNI_TRUE);
+    if export == JNI_TRUE {
+        wgpu_obj.canvas.set_export_mirror(mirror);
+    } else {
+        wgpu_obj.canvas.set_preview_mirror(mirror);
+    }
 }
 
 #[no_mangle]
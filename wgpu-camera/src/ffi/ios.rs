@@ -1,4 +1,5 @@
 use crate::wgpu_canvas::WgpuCanvas;
+use crate::Preset;
 use app_surface::{AppSurface, IOSViewObj};
 use std::ffi::c_void;
 use std::os::raw::c_char;
@@ -26,12 +27,337 @@ pub fn set_filter(
     wgpu_obj.set_filter(ty, opaque_background_color, param);
 }
 
+/// Replace the filter chain with a single `ty` stage, blending to it over `duration` seconds
+#[no_mangle]
+pub fn set_filter_animated(
+    wgpu_obj: *mut c_void,
+    ty: crate::FilterType,
+    duration: f32,
+    transition: crate::Transition,
+) {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    wgpu_obj.set_filter_animated(ty, duration, transition);
+}
+
+#[no_mangle]
+pub fn begin_page_curl(wgpu_obj: *mut c_void, ty: crate::FilterType) {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    wgpu_obj.begin_page_curl(ty);
+}
+
+#[no_mangle]
+pub fn drag_page_curl(wgpu_obj: *mut c_void, x: f32, y: f32) {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    wgpu_obj.drag_page_curl(x, y);
+}
+
+#[no_mangle]
+pub fn end_page_curl(wgpu_obj: *mut c_void, duration: f32) {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    wgpu_obj.end_page_curl(duration);
+}
+
+#[no_mangle]
+pub fn begin_comparison(wgpu_obj: *mut c_void, divider: crate::CompareDivider) {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    wgpu_obj.set_comparison(Some(divider));
+}
+
+#[no_mangle]
+pub fn drag_comparison(wgpu_obj: *mut c_void, x: f32, y: f32) {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    wgpu_obj.drag_comparison(x, y);
+}
+
+#[no_mangle]
+pub fn end_comparison(wgpu_obj: *mut c_void) {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    wgpu_obj.set_comparison(None);
+}
+
+/// Draw `tex_key` with `ty` into the normalized rectangle `(x, y, width, height)` of the
+/// surface, on top of the layers with a lower `z_index`
+#[no_mangle]
+pub fn add_layer(
+    wgpu_obj: *mut c_void,
+    tex_key: *const c_char,
+    ty: crate::FilterType,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    z_index: i32,
+) {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    let tex_key = crate::cchar_to_string(tex_key);
+    let mut layer = crate::Layer::new(&tex_key, ty, (x, y, width, height));
+    layer.z_index = z_index;
+    wgpu_obj.add_layer(layer);
+}
+
+#[no_mangle]
+pub fn clear_layers(wgpu_obj: *mut c_void) {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    wgpu_obj.set_layers(vec![]);
+}
+
+/// Returns 1 on success, 0 if the shader failed to validate (the reason is logged)
+#[no_mangle]
+pub fn register_filter(
+    wgpu_obj: *mut c_void,
+    name: *const c_char,
+    wgsl_source: *const c_char,
+) -> i32 {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    let name = crate::cchar_to_string(name);
+    let wgsl_source = crate::cchar_to_string(wgsl_source);
+    match wgpu_obj.register_filter(&name, &wgsl_source) {
+        Ok(_) => 1,
+        Err(e) => {
+            log::error!("register_filter {}: {}", name, e);
+            0
+        }
+    }
+}
+
+#[no_mangle]
+pub fn set_custom_filter(
+    wgpu_obj: *mut c_void,
+    name: *const c_char,
+    opaque_background_color: i32,
+    param: f32,
+) -> i32 {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    let name = crate::cchar_to_string(name);
+    wgpu_obj.set_custom_filter(&name, opaque_background_color == 1, param) as i32
+}
+
+/// Grade the last stage, a `Lut3D` filter, with the `.cube` file `text`
+#[no_mangle]
+pub fn set_lut_cube(wgpu_obj: *mut c_void, text: *const c_char) -> i32 {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    let text = crate::cchar_to_string(text);
+    match crate::Lut3D::from_cube(&text) {
+        Ok(lut) => set_last_stage_lut(wgpu_obj, &lut),
+        Err(e) => {
+            log::error!("set_lut_cube: {}", e);
+            0
+        }
+    }
+}
+
+/// Grade the last stage, a `Lut3D` filter, with the Hald CLUT PNG in `data`
+#[no_mangle]
+pub fn set_lut_hald_png(wgpu_obj: *mut c_void, data: *const u8, len: usize) -> i32 {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    let data: &[u8] = if len == 0 {
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(data, len) }
+    };
+    match crate::Lut3D::from_hald_png(data) {
+        Ok(lut) => set_last_stage_lut(wgpu_obj, &lut),
+        Err(e) => {
+            log::error!("set_lut_hald_png: {}", e);
+            0
+        }
+    }
+}
+
+fn set_last_stage_lut(wgpu_obj: &mut WgpuCanvas, lut: &crate::Lut3D) -> i32 {
+    let index = wgpu_obj.filters().len().saturating_sub(1);
+    wgpu_obj.set_lut(index, lut) as i32
+}
+
+/// Replace one curve of the last stage, a `ToneCurve` filter, with `count` `(input, output)`
+/// pairs read from `points`
+#[no_mangle]
+pub fn set_tone_curve_points(
+    wgpu_obj: *mut c_void,
+    channel: crate::CurveChannel,
+    points: *const f32,
+    count: usize,
+) -> i32 {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    let points = if count == 0 {
+        vec![]
+    } else {
+        unsafe { std::slice::from_raw_parts(points, count * 2) }
+            .chunks_exact(2)
+            .map(|p| (p[0], p[1]))
+            .collect()
+    };
+    let index = wgpu_obj.filters().len().saturating_sub(1);
+    let mut curves = match wgpu_obj.filter_params(index) {
+        Some(crate::FilterParams::ToneCurve(params)) => params.curves.clone(),
+        _ => return 0,
+    };
+    curves.set_points(channel, points);
+    wgpu_obj.set_tone_curve(index, curves) as i32
+}
+
+#[no_mangle]
+pub fn set_levels(wgpu_obj: *mut c_void, black_point: f32, white_point: f32, gamma: f32) -> i32 {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    let index = wgpu_obj.filters().len().saturating_sub(1);
+    wgpu_obj.set_levels(index, black_point, white_point, gamma) as i32
+}
+
+/// Quantize the last stage, a `Dither` filter, to `count` colors packed as `0xRRGGBB`, a
+/// `count` of 0 goes back to per channel levels
+#[no_mangle]
+pub fn set_dither_palette_colors(wgpu_obj: *mut c_void, colors: *const u32, count: usize) -> i32 {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    let colors = if count == 0 {
+        vec![]
+    } else {
+        unsafe { std::slice::from_raw_parts(colors, count) }
+            .iter()
+            .map(|c| [(c >> 16) as u8, (c >> 8) as u8, *c as u8])
+            .collect()
+    };
+    match crate::Palette::new(colors) {
+        Ok(palette) => set_last_stage_palette(wgpu_obj, palette),
+        Err(e) => {
+            log::error!("set_dither_palette_colors: {}", e);
+            0
+        }
+    }
+}
+
+/// Quantize the last stage, a `Dither` filter, to the distinct opaque colors of a swatch PNG
+#[no_mangle]
+pub fn set_dither_palette_png(wgpu_obj: *mut c_void, data: *const u8, len: usize) -> i32 {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    let data: &[u8] = if len == 0 {
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(data, len) }
+    };
+    match crate::Palette::from_png_swatch(data) {
+        Ok(palette) => set_last_stage_palette(wgpu_obj, palette),
+        Err(e) => {
+            log::error!("set_dither_palette_png: {}", e);
+            0
+        }
+    }
+}
+
+fn set_last_stage_palette(wgpu_obj: &mut WgpuCanvas, palette: crate::Palette) -> i32 {
+    let index = wgpu_obj.filters().len().saturating_sub(1);
+    wgpu_obj.set_dither_palette(index, palette) as i32
+}
+
+/// JSON array of the filter schemas, release it with `free_rust_string`
+#[no_mangle]
+pub fn filter_schemas(wgpu_obj: *mut c_void) -> *mut c_char {
+    let wgpu_obj = unsafe { &*(wgpu_obj as *mut WgpuCanvas) };
+    let json = serde_json::to_string(&wgpu_obj.filter_schemas()).unwrap_or_default();
+    crate::string_to_cchar(json)
+}
+
+/// The current filter chain as preset JSON, release it with `free_rust_string`
+#[no_mangle]
+pub fn export_preset(wgpu_obj: *mut c_void, name: *const c_char) -> *mut c_char {
+    let wgpu_obj = unsafe { &*(wgpu_obj as *mut WgpuCanvas) };
+    let name = crate::cchar_to_string(name);
+    let json = wgpu_obj.export_preset(&name).to_json().unwrap_or_default();
+    crate::string_to_cchar(json)
+}
+
+#[no_mangle]
+pub fn apply_preset(wgpu_obj: *mut c_void, json: *const c_char) -> i32 {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    let json = crate::cchar_to_string(json);
+    match Preset::from_json(&json).and_then(|preset| wgpu_obj.apply_preset(&preset)) {
+        Ok(_) => 1,
+        Err(e) => {
+            log::error!("apply_preset: {}", e);
+            0
+        }
+    }
+}
+
+#[no_mangle]
+pub fn free_rust_string(s: *mut c_char) {
+    if !s.is_null() {
+        let _ = unsafe { std::ffi::CString::from_raw(s) };
+    }
+}
+
 #[no_mangle]
 pub fn change_filter_param(wgpu_obj: *mut c_void, param: f32) {
     let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
     wgpu_obj.change_filter_param(param);
 }
 
+#[no_mangle]
+pub fn set_content_mode(wgpu_obj: *mut c_void, mode: crate::ContentMode) {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    wgpu_obj.set_content_mode(mode);
+}
+
+/// A `width` or `height` of 0 removes the crop
+#[no_mangle]
+pub fn set_source_crop(wgpu_obj: *mut c_void, x: f32, y: f32, width: f32, height: f32) {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    let crop = if width > 0.0 && height > 0.0 {
+        Some(crate::CropRect::new(x, y, width, height))
+    } else {
+        None
+    };
+    wgpu_obj.set_source_crop(crop);
+}
+
+/// Clockwise rotation of the display in degrees, e.g. 90 for landscape right
+#[no_mangle]
+pub fn set_device_rotation(wgpu_obj: *mut c_void, angle: f32) {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    wgpu_obj.set_device_rotation(angle);
+}
+
+/// `export` selects the mirroring of `capture_frame` instead of the preview
+#[no_mangle]
+pub fn set_mirror(wgpu_obj: *mut c_void, horizontal: i32, vertical: i32, export: i32) {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    let mirror = crate::Mirror::new(horizontal > 0, vertical > 0);
+    if export > 0 {
+        wgpu_obj.set_export_mirror(mirror);
+    } else {
+        wgpu_obj.set_preview_mirror(mirror);
+    }
+}
+
+/// `tx`, `ty` in surface pixels, `rotation` in clockwise degrees
+#[no_mangle]
+pub fn set_view_transform(wgpu_obj: *mut c_void, scale: f32, tx: f32, ty: f32, rotation: f32) {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    wgpu_obj.set_view_transform(scale, (tx, ty), rotation);
+}
+
+/// Writes the source texture position of a surface position into `out_x`, `out_y`,
+/// returns 0 if it is outside the image
+#[no_mangle]
+pub fn view_to_image(
+    wgpu_obj: *mut c_void,
+    x: f32,
+    y: f32,
+    out_x: *mut f32,
+    out_y: *mut f32,
+) -> i32 {
+    let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
+    match wgpu_obj.view_to_image(x, y) {
+        Some((ix, iy)) => {
+            unsafe {
+                *out_x = ix;
+                *out_y = iy;
+            }
+            1
+        }
+        None => 0,
+    }
+}
+
 #[no_mangle]
 pub fn remove_texture(wgpu_obj: *mut c_void, tex_key: *const c_char) {
     let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
@@ -78,27 +404,25 @@ pub fn set_external_texture(
                 depth: 1,
             },
         );
-        obj.app_surface
-            .device
-            .create_texture_from_hal::<hal::api::Metal>(
-                hal_tex,
-                &wgpu::TextureDescriptor {
-                    label: None,
-                    size: texture_extent,
-                    mip_level_count: 1,
-                    sample_count: 1,
-                    dimension: wgpu::TextureDimension::D2,
-                    format: wgpu::TextureFormat::Bgra8Unorm,
-                    usage: wgpu::TextureUsages::TEXTURE_BINDING,
-                },
-            )
+        obj.device().create_texture_from_hal::<hal::api::Metal>(
+            hal_tex,
+            &wgpu::TextureDescriptor {
+                label: None,
+                size: texture_extent,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Bgra8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+            },
+        )
     };
     obj.set_external_texture(external_texture, tex_key, (width as f32, height as f32));
 }
 
 #[no_mangle]
-pub fn enter_frame(wgpu_obj: *mut c_void, tex_key: *const c_char) {
+pub fn enter_frame(wgpu_obj: *mut c_void, tex_key: *const c_char, timestamp: f64) {
     let wgpu_obj = unsafe { &mut *(wgpu_obj as *mut WgpuCanvas) };
     let tex_key = crate::cchar_to_string(tex_key);
-    wgpu_obj.enter_frame(tex_key);
+    wgpu_obj.enter_frame(tex_key, timestamp);
 }
@@ -1,6 +1,9 @@
 // Desktop
 
-pub fn get_a_texture(app_surface: &app_surface::AppSurface) -> (wgpu::Texture, wgpu::Extent3d) {
+pub fn get_a_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> (wgpu::Texture, wgpu::Extent3d) {
     // let img_name = "pepper512x512";
     // let img_name = "kodim768x512";
     // let img_name = "768*480";
@@ -19,7 +22,7 @@ pub fn get_a_texture(app_surface: &app_surface::AppSurface) -> (wgpu::Texture, w
         height: info.height,
         depth_or_array_layers: 1,
     };
-    let texture = app_surface.device.create_texture(&wgpu::TextureDescriptor {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
         label: None,
         size,
         mip_level_count: 1,
@@ -28,7 +31,7 @@ pub fn get_a_texture(app_surface: &app_surface::AppSurface) -> (wgpu::Texture, w
         format: wgpu::TextureFormat::Rgba8UnormSrgb,
         usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
     });
-    app_surface.queue.write_texture(
+    queue.write_texture(
         texture.as_image_copy(),
         &buf,
         wgpu::ImageDataLayout {
@@ -0,0 +1,285 @@
+use crate::{
+    filter_params::{FilterParams, MAX_PARAMS_LEN},
+    texture_pool::{PooledTexture, TexturePool},
+    FilterNode, FilterType,
+};
+use idroid::BufferObj;
+use std::collections::HashMap;
+use std::rc::Rc;
+use wgpu::Buffer;
+
+/// An external texture bound to the filter chains and layers
+pub(crate) struct SourceTexture {
+    pub _texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    // 采样 sRGB 格式的纹理时 GPU 已经把颜色转换到了线性空间
+    pub srgb: bool,
+    pub size: (u32, u32),
+}
+
+pub(crate) struct FilterStage {
+    pub filter: FilterType,
+    pub node: Box<dyn FilterNode>,
+    pub params_buffer: BufferObj,
+    pub params: FilterParams,
+    // FilterType::Custom 对应的已注册滤镜名
+    pub custom_name: Option<String>,
+    // 输入与输出纹理是否为 sRGB 格式，是的话采样与写入时 GPU 已经做了转换
+    srgb: (bool, bool),
+}
+
+impl FilterStage {
+    pub fn new(device: &wgpu::Device, filter: FilterType, mut node: Box<dyn FilterNode>) -> Self {
+        let storage_data = [0.0_f32; MAX_PARAMS_LEN];
+        let mut params_buffer =
+            BufferObj::create_storage_buffer(device, &storage_data, Some("Param Buffer"));
+        params_buffer.read_only = true;
+        let params = FilterParams::default_for(filter);
+        node.update_params(&params);
+        Self {
+            filter,
+            node,
+            params_buffer,
+            params,
+            custom_name: None,
+            srgb: (false, false),
+        }
+    }
+
+    /// Replace the params, clamped to their schema, and write them to the params buffer; the tone
+    /// curve texture is only uploaded again when its points change
+    pub fn set_params(&mut self, queue: &wgpu::Queue, mut params: FilterParams) {
+        params.clamp_to_schema();
+        if let (FilterParams::ToneCurve(old), FilterParams::ToneCurve(new)) =
+            (&self.params, &params)
+        {
+            if old.curves != new.curves {
+                if let Some(texture) = self.node.aux_texture() {
+                    new.curves.write_texture(queue, texture);
+                }
+            }
+        }
+        self.node.update_params(&params);
+        self.params = params;
+        self.write_params(queue);
+    }
+
+    /// Whether the input and the target texture of the stage have an sRGB format, filters that
+    /// work in linear light only convert the colors the GPU does not convert for them
+    pub fn set_srgb(&mut self, queue: &wgpu::Queue, input: bool, target: bool) {
+        if self.srgb != (input, target) {
+            self.srgb = (input, target);
+            self.write_params(queue);
+        }
+    }
+
+    pub fn write_params(&self, queue: &wgpu::Queue) {
+        let mut data = self.params.storage_data();
+        // color_adjust.wgsl 的 InputParams 末尾是两个 sRGB 标记
+        if let FilterParams::ColorAdjust(_) = self.params {
+            let (input, target) = self.srgb;
+            data.extend_from_slice(bytemuck::cast_slice(&[
+                input as u32 as f32,
+                target as u32 as f32,
+            ]));
+        }
+        queue.write_buffer(&self.params_buffer.buffer, 0, &data);
+    }
+}
+
+/// 按顺序执行的滤镜链
+///
+/// 除最后一个 stage 外，每个 stage 都渲染到一张与源纹理同尺寸的中间纹理，由下一个 stage 采样；
+/// 只有最后一个 stage 绘制到 frame view 上。
+pub(crate) struct FilterChain {
+    pub stages: Vec<FilterStage>,
+    // 中间 pass 不应用相机传感器的旋转，只在最后一个 stage 上应用一次
+    plain_mvp: Rc<BufferObj>,
+    pool: TexturePool,
+    intermediates: HashMap<String, Vec<PooledTexture>>,
+    external_sampler: Option<Rc<wgpu::Sampler>>,
+    viewport: (f32, f32, f32, f32),
+}
+
+impl FilterChain {
+    pub fn new(format: wgpu::TextureFormat, plain_mvp: BufferObj) -> Self {
+        Self {
+            stages: vec![],
+            plain_mvp: Rc::new(plain_mvp),
+            pool: TexturePool::new(format),
+            intermediates: HashMap::new(),
+            external_sampler: None,
+            viewport: (0.0, 0.0, 0.0, 0.0),
+        }
+    }
+
+    pub fn filters(&self) -> Vec<FilterType> {
+        self.stages.iter().map(|stage| stage.filter).collect()
+    }
+
+    pub fn set_viewport(&mut self, viewport: (f32, f32, f32, f32)) {
+        self.viewport = viewport;
+    }
+
+    pub fn set_external_sampler(&mut self, sampler: wgpu::Sampler) {
+        self.external_sampler = Some(Rc::new(sampler));
+    }
+
+    pub fn external_sampler(&self) -> Option<Rc<wgpu::Sampler>> {
+        self.external_sampler.clone()
+    }
+
+    pub fn insert(&mut self, index: usize, stage: FilterStage) {
+        let index = index.min(self.stages.len());
+        self.stages.insert(index, stage);
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<FilterStage> {
+        if index < self.stages.len() {
+            Some(self.stages.remove(index))
+        } else {
+            None
+        }
+    }
+
+    pub fn move_stage(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.stages.len() || to >= self.stages.len() {
+            return false;
+        }
+        let stage = self.stages.remove(from);
+        self.stages.insert(to, stage);
+        true
+    }
+
+    /// Move the stages and their intermediate textures into a new chain that keeps rendering
+    /// them, this chain is left empty
+    pub fn split_off(&mut self, format: wgpu::TextureFormat) -> FilterChain {
+        Self {
+            stages: std::mem::take(&mut self.stages),
+            plain_mvp: self.plain_mvp.clone(),
+            pool: TexturePool::new(format),
+            intermediates: std::mem::take(&mut self.intermediates),
+            external_sampler: self.external_sampler.clone(),
+            viewport: self.viewport,
+        }
+    }
+
+    /// An empty chain that shares the mvp and the external sampler of this one
+    pub fn fork(&self, format: wgpu::TextureFormat) -> FilterChain {
+        Self {
+            stages: vec![],
+            plain_mvp: self.plain_mvp.clone(),
+            pool: TexturePool::new(format),
+            intermediates: HashMap::new(),
+            external_sampler: self.external_sampler.clone(),
+            viewport: self.viewport,
+        }
+    }
+
+    /// Take over the intermediate textures of a chain that is no longer used
+    pub fn recycle(&mut self, other: FilterChain) {
+        for target in other.intermediates.into_values().flatten() {
+            self.pool.release(target);
+        }
+    }
+
+    /// Bind `source` as the input of the first stage and (re)wire the intermediate
+    /// textures between the following stages
+    pub fn bind_source(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mvp_buffer: &Buffer,
+        tex_key: &str,
+        source: &SourceTexture,
+    ) {
+        let size = source.size;
+        let count = self.stages.len();
+        if count == 0 {
+            return;
+        }
+        let targets = self.intermediates.entry(tex_key.to_string()).or_default();
+        if targets.iter().any(|target| target.size != size) {
+            for target in targets.drain(..) {
+                self.pool.release(target);
+            }
+        }
+        while targets.len() > count - 1 {
+            self.pool.release(targets.pop().unwrap());
+        }
+        while targets.len() < count - 1 {
+            targets.push(self.pool.acquire(device, size));
+        }
+
+        for (i, stage) in self.stages.iter_mut().enumerate() {
+            // 外部 sampler（如 Android 上的 YCbCr sampler）只用于采样源纹理
+            stage.node.update_sampler(if i == 0 {
+                self.external_sampler.clone()
+            } else {
+                None
+            });
+            let mvp = if i + 1 == count {
+                mvp_buffer
+            } else {
+                &self.plain_mvp.buffer
+            };
+            let input_view = if i == 0 {
+                &source.view
+            } else {
+                &targets[i - 1].view
+            };
+            // 中间纹理与最终目标都是 pool 的格式
+            let target_srgb = self.pool.format().describe().srgb;
+            let input_srgb = if i == 0 { source.srgb } else { target_srgb };
+            stage.set_srgb(queue, input_srgb, target_srgb);
+            stage.node.update_bind_group(
+                device,
+                mvp,
+                &stage.params_buffer.buffer,
+                input_view,
+                size,
+                tex_key.to_string(),
+            );
+        }
+    }
+
+    pub fn remove_source(&mut self, tex_key: &str) {
+        if let Some(targets) = self.intermediates.remove(tex_key) {
+            for target in targets {
+                self.pool.release(target);
+            }
+        }
+        for stage in self.stages.iter_mut() {
+            stage.node.remove_bind_group(tex_key.to_string());
+        }
+    }
+
+    pub fn enter_frame(
+        &mut self,
+        frame_view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+        tex_key: &str,
+    ) {
+        let count = self.stages.len();
+        let targets = self.intermediates.get(tex_key);
+        for (i, stage) in self.stages.iter_mut().enumerate() {
+            if i + 1 == count {
+                stage.node.update_viewport(self.viewport);
+                stage
+                    .node
+                    .enter_frame(frame_view, encoder, tex_key.to_string());
+            } else {
+                let target = match targets.and_then(|targets| targets.get(i)) {
+                    Some(target) => target,
+                    None => return,
+                };
+                stage
+                    .node
+                    .update_viewport((0.0, 0.0, target.size.0 as f32, target.size.1 as f32));
+                stage
+                    .node
+                    .enter_frame(&target.view, encoder, tex_key.to_string());
+            }
+        }
+    }
+}
@@ -0,0 +1,533 @@
+use crate::{
+    compute_filter_node::PassDispatch,
+    filter_schema::{filter_schema, ParamType},
+    FilterType, Palette, ToneCurve,
+};
+use bytemuck::{Pod, Zeroable};
+use serde::{Deserialize, Serialize};
+
+// params buffer 可容纳 64 个 f32：radius, sigma 与 radius + 1 个权重
+pub(crate) const MAX_BLUR_RADIUS: f32 = 32.0;
+pub(crate) const MAX_PARAMS_LEN: usize = 64;
+/// Most colors a `Palette` can have, the rest of the params buffer after the dither settings
+pub const MAX_PALETTE_COLORS: usize = MAX_PARAMS_LEN - 6;
+
+/// `InputParams` of `ascii_art.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AsciiArtParams {
+    /// Width and height of a character cell, in pixels of the source texture
+    pub cell_size: f32,
+}
+
+impl Default for AsciiArtParams {
+    fn default() -> Self {
+        Self { cell_size: 8.0 }
+    }
+}
+
+/// `InputParams` of `cross_hatching.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrossHatchParams {
+    /// How close together hatch lines are placed, in pixels
+    pub density: f32,
+    /// How wide hatch lines are drawn, in pixels
+    pub line_width: f32,
+    /// The brightnesses below which each of the four hatch directions appears
+    pub thresholds: [f32; 4],
+    pub edge_noise_suppression: f32,
+    /// 1.0 draws the white background, 0.0 leaves it transparent
+    pub opaque_background_color: f32,
+}
+
+impl Default for CrossHatchParams {
+    fn default() -> Self {
+        Self {
+            density: 10.0,
+            line_width: 0.8,
+            thresholds: [0.8, 0.6, 0.3, 0.15],
+            edge_noise_suppression: 0.125,
+            opaque_background_color: 0.0,
+        }
+    }
+}
+
+/// `InputParams` of `halftone.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HalftoneParams {
+    /// Width and height of a screen cell, in pixels of the source texture
+    pub cell_size: f32,
+    /// 1.0 separates the image into cyan, magenta, yellow and black screens, 0.0 uses a single
+    /// screen of `ink_color`
+    pub cmyk: f32,
+    /// 0.0 round, 1.0 line, 2.0 diamond
+    pub dot_shape: f32,
+    /// Angle of the single ink screen, in degrees
+    pub angle: f32,
+    /// Angles of the cyan, magenta, yellow and black screens, in degrees
+    pub angles: [f32; 4],
+    pub paper_color: [f32; 3],
+    /// Color of the single ink screen and of the black separation
+    pub ink_color: [f32; 3],
+    /// 1.0 draws the paper color, 0.0 leaves the paper transparent
+    pub opaque_background_color: f32,
+}
+
+impl Default for HalftoneParams {
+    fn default() -> Self {
+        Self {
+            cell_size: 8.0,
+            cmyk: 0.0,
+            dot_shape: 0.0,
+            angle: 45.0,
+            angles: [15.0, 75.0, 0.0, 45.0],
+            paper_color: [1.0, 1.0, 1.0],
+            ink_color: [0.0, 0.0, 0.0],
+            opaque_background_color: 0.0,
+        }
+    }
+}
+
+/// `InputParams` of `edge_detection.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EdgeDetectionParams {
+    pub noise_suppression: f32,
+    /// 1.0 draws white edges on black, 0.0 draws black edges on a transparent background
+    pub opaque_background_color: f32,
+}
+
+impl Default for EdgeDetectionParams {
+    fn default() -> Self {
+        Self {
+            noise_suppression: 0.05,
+            opaque_background_color: 0.0,
+        }
+    }
+}
+
+/// Header of `BlurParams` in `gaussian_blur_x.wgsl`, the kernel weights are derived from it
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GaussianBlurParams {
+    /// In pixels, rounded and clamped to `1..=32`
+    pub radius: f32,
+    /// 0 derives sigma from the radius
+    pub sigma: f32,
+}
+
+impl Default for GaussianBlurParams {
+    fn default() -> Self {
+        Self {
+            radius: 4.0,
+            sigma: 0.0,
+        }
+    }
+}
+
+/// `InputParams` of `lut3d.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Lut3DParams {
+    /// Blends from the original (0.0) to the graded color (1.0)
+    pub intensity: f32,
+}
+
+impl Default for Lut3DParams {
+    fn default() -> Self {
+        Self { intensity: 1.0 }
+    }
+}
+
+/// `InputParams` of `color_adjust.wgsl`, every adjustment is neutral at its default
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorAdjustParams {
+    /// Exposure compensation in EV
+    pub exposure: f32,
+    /// `-1.0..=1.0`, scales the tones around middle grey
+    pub contrast: f32,
+    /// `-1.0..=1.0`, -1.0 is grayscale
+    pub saturation: f32,
+    /// `-1.0..=1.0`, like saturation but mostly affects muted colors
+    pub vibrance: f32,
+    /// Exposure of the bright tones in EV, negative values recover highlights
+    pub highlights: f32,
+    /// Exposure of the dark tones in EV, positive values lift shadows
+    pub shadows: f32,
+    /// `-1.0..=1.0`, warmer (yellow) for positive values, cooler (blue) for negative ones
+    pub temperature: f32,
+    /// `-1.0..=1.0`, magenta for positive values, green for negative ones
+    pub tint: f32,
+    /// Values above 1.0 brighten the midtones
+    pub gamma: f32,
+}
+
+impl Default for ColorAdjustParams {
+    fn default() -> Self {
+        Self {
+            exposure: 0.0,
+            contrast: 0.0,
+            saturation: 0.0,
+            vibrance: 0.0,
+            highlights: 0.0,
+            shadows: 0.0,
+            temperature: 0.0,
+            tint: 0.0,
+            gamma: 1.0,
+        }
+    }
+}
+
+/// Levels and curves of `tone_curve.wgsl`, only the levels are written to `InputParams`, the
+/// curves are baked into a lookup texture
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToneCurveParams {
+    /// Input value mapped to black
+    pub black_point: f32,
+    /// Input value mapped to white
+    pub white_point: f32,
+    /// Applied between the levels and the curves, values above 1.0 brighten the midtones
+    pub gamma: f32,
+    pub curves: ToneCurve,
+}
+
+impl Default for ToneCurveParams {
+    fn default() -> Self {
+        Self {
+            black_point: 0.0,
+            white_point: 1.0,
+            gamma: 1.0,
+            curves: ToneCurve::default(),
+        }
+    }
+}
+
+/// How `FilterType::Dither` spreads the quantization error
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DitherMethod {
+    /// Ordered dithering with a Bayer matrix
+    #[default]
+    Bayer,
+    /// Ordered dithering with a blue noise threshold matrix, less regular than Bayer
+    BlueNoise,
+    /// Error diffusion to the right and the next row
+    FloydSteinberg,
+    /// Error diffusion that drops a quarter of the error, for higher contrast
+    Atkinson,
+}
+
+/// `DitherParams` of `common/dither.wgsl`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DitherParams {
+    pub method: DitherMethod,
+    /// Width and height of the output pixels, in pixels of the source texture
+    pub pixel_size: f32,
+    /// Edge length of the Bayer matrix, 2, 4 or 8
+    pub bayer_size: f32,
+    /// Levels of each channel when the palette is empty
+    pub levels: f32,
+    /// Amplitude of the ordered dithering thresholds, 0 derives it from the palette or levels
+    pub spread: f32,
+    pub palette: Palette,
+}
+
+impl Default for DitherParams {
+    fn default() -> Self {
+        Self {
+            method: DitherMethod::Bayer,
+            pixel_size: 2.0,
+            bayer_size: 4.0,
+            levels: 2.0,
+            spread: 0.0,
+            palette: Palette::default(),
+        }
+    }
+}
+
+/// Parameters of a filter stage, the variant must match the stage's `FilterType`
+#[derive(Clone, Debug, PartialEq)]
+pub enum FilterParams {
+    Original,
+    AsciiArt(AsciiArtParams),
+    CrossHatch(CrossHatchParams),
+    EdgeDetection(EdgeDetectionParams),
+    GaussianBlur(GaussianBlurParams),
+    /// Raw `InputParams` of a custom filter, at most 64 f32; the default layout is
+    /// `[param, opaque_background_color]`
+    Custom(Vec<f32>),
+    Lut3D(Lut3DParams),
+    ColorAdjust(ColorAdjustParams),
+    ToneCurve(ToneCurveParams),
+    Halftone(HalftoneParams),
+    Dither(DitherParams),
+}
+
+impl FilterParams {
+    pub fn default_for(ty: FilterType) -> Self {
+        match ty {
+            FilterType::Original => Self::Original,
+            FilterType::AsciiArt => Self::AsciiArt(AsciiArtParams::default()),
+            FilterType::CrossHatch => Self::CrossHatch(CrossHatchParams::default()),
+            FilterType::EdgeDetection => Self::EdgeDetection(EdgeDetectionParams::default()),
+            FilterType::GaussianBlur => Self::GaussianBlur(GaussianBlurParams::default()),
+            FilterType::Custom => Self::Custom(vec![0.0, 0.0]),
+            FilterType::Lut3D => Self::Lut3D(Lut3DParams::default()),
+            FilterType::ColorAdjust => Self::ColorAdjust(ColorAdjustParams::default()),
+            FilterType::ToneCurve => Self::ToneCurve(ToneCurveParams::default()),
+            FilterType::Halftone => Self::Halftone(HalftoneParams::default()),
+            FilterType::Dither => Self::Dither(DitherParams::default()),
+        }
+    }
+
+    pub fn filter_type(&self) -> FilterType {
+        match self {
+            Self::Original => FilterType::Original,
+            Self::AsciiArt(_) => FilterType::AsciiArt,
+            Self::CrossHatch(_) => FilterType::CrossHatch,
+            Self::EdgeDetection(_) => FilterType::EdgeDetection,
+            Self::GaussianBlur(_) => FilterType::GaussianBlur,
+            Self::Custom(_) => FilterType::Custom,
+            Self::Lut3D(_) => FilterType::Lut3D,
+            Self::ColorAdjust(_) => FilterType::ColorAdjust,
+            Self::ToneCurve(_) => FilterType::ToneCurve,
+            Self::Halftone(_) => FilterType::Halftone,
+            Self::Dither(_) => FilterType::Dither,
+        }
+    }
+
+    /// Apply the single `input_param` knob of `set_filter`/`change_filter_param`, clamped to the
+    /// range of its schema, 0 selects the schema default unless the schema marks it as
+    /// `zero_is_valid`. Fields that the knob does not cover are kept.
+    ///
+    /// The knob of a custom filter is passed to its shader unchanged.
+    pub(crate) fn apply_input_param(
+        &mut self,
+        input_param: f32,
+        scale_factor: f32,
+        opaque_background_color: bool,
+    ) {
+        let schema = filter_schema(self.filter_type(), scale_factor);
+        let value = match schema.input_param_schema() {
+            // 自定义滤镜的参数含义由 shader 决定，原样传入
+            _ if matches!(self, Self::Custom(_)) => input_param,
+            Some(param) if input_param == 0.0 && !param.zero_is_valid => param.default,
+            Some(param) => param.clamp(input_param),
+            None => input_param,
+        };
+        let opaque_background_color = if opaque_background_color { 1.0 } else { 0.0 };
+        match self {
+            Self::Original => {}
+            Self::AsciiArt(p) => p.cell_size = value,
+            Self::CrossHatch(p) => {
+                p.density = value;
+                p.line_width = p.density * 0.08;
+                p.opaque_background_color = opaque_background_color;
+            }
+            Self::EdgeDetection(p) => {
+                p.noise_suppression = value;
+                p.opaque_background_color = opaque_background_color;
+            }
+            Self::GaussianBlur(p) => p.radius = value,
+            Self::Custom(data) => {
+                if data.len() < 2 {
+                    data.resize(2, 0.0);
+                }
+                data[0] = value;
+                data[1] = opaque_background_color;
+            }
+            Self::Lut3D(p) => p.intensity = value,
+            Self::ColorAdjust(p) => p.exposure = value,
+            Self::ToneCurve(p) => p.gamma = value,
+            Self::Halftone(p) => {
+                p.cell_size = value;
+                p.opaque_background_color = opaque_background_color;
+            }
+            Self::Dither(p) => p.pixel_size = value,
+        }
+    }
+
+    /// Clamp every field to the range of its schema, so that e.g. a zero cell size never reaches
+    /// a shader that divides by it. The palette, curves and raw custom params are kept as they are.
+    pub(crate) fn clamp_to_schema(&mut self) {
+        // 参数范围与 scale_factor 无关
+        let schema = filter_schema(self.filter_type(), 1.0);
+        for (name, value) in self.fields_mut() {
+            if let Some(param) = schema.param(&name) {
+                *value = match param.ty {
+                    ParamType::Float => param.clamp(*value),
+                    ParamType::Int | ParamType::Bool => param.clamp(value.round()),
+                };
+            }
+        }
+    }
+
+    // 与 schema 中的参数一一对应的字段，数组字段按 `name[i]` 展开
+    fn fields_mut(&mut self) -> Vec<(String, &mut f32)> {
+        fn field<'a>(name: &str, value: &'a mut f32) -> (String, &'a mut f32) {
+            (name.to_string(), value)
+        }
+        fn array<'a>(name: &str, values: &'a mut [f32]) -> Vec<(String, &'a mut f32)> {
+            values
+                .iter_mut()
+                .enumerate()
+                .map(|(i, value)| (format!("{}[{}]", name, i), value))
+                .collect()
+        }
+        match self {
+            Self::Original | Self::Custom(_) => vec![],
+            Self::AsciiArt(p) => vec![field("cell_size", &mut p.cell_size)],
+            Self::CrossHatch(p) => {
+                let mut fields = vec![
+                    field("density", &mut p.density),
+                    field("line_width", &mut p.line_width),
+                    field("edge_noise_suppression", &mut p.edge_noise_suppression),
+                    field("opaque_background_color", &mut p.opaque_background_color),
+                ];
+                fields.extend(array("thresholds", &mut p.thresholds));
+                fields
+            }
+            Self::EdgeDetection(p) => vec![
+                field("noise_suppression", &mut p.noise_suppression),
+                field("opaque_background_color", &mut p.opaque_background_color),
+            ],
+            Self::GaussianBlur(p) => {
+                vec![field("radius", &mut p.radius), field("sigma", &mut p.sigma)]
+            }
+            Self::Lut3D(p) => vec![field("intensity", &mut p.intensity)],
+            Self::ColorAdjust(p) => vec![
+                field("exposure", &mut p.exposure),
+                field("contrast", &mut p.contrast),
+                field("saturation", &mut p.saturation),
+                field("vibrance", &mut p.vibrance),
+                field("highlights", &mut p.highlights),
+                field("shadows", &mut p.shadows),
+                field("temperature", &mut p.temperature),
+                field("tint", &mut p.tint),
+                field("gamma", &mut p.gamma),
+            ],
+            Self::ToneCurve(p) => vec![
+                field("black_point", &mut p.black_point),
+                field("white_point", &mut p.white_point),
+                field("gamma", &mut p.gamma),
+            ],
+            Self::Halftone(p) => {
+                let mut fields = vec![
+                    field("cell_size", &mut p.cell_size),
+                    field("cmyk", &mut p.cmyk),
+                    field("dot_shape", &mut p.dot_shape),
+                    field("angle", &mut p.angle),
+                    field("opaque_background_color", &mut p.opaque_background_color),
+                ];
+                fields.extend(array("angles", &mut p.angles));
+                fields.extend(array("paper_color", &mut p.paper_color));
+                fields.extend(array("ink_color", &mut p.ink_color));
+                fields
+            }
+            Self::Dither(p) => vec![
+                field("pixel_size", &mut p.pixel_size),
+                field("bayer_size", &mut p.bayer_size),
+                field("levels", &mut p.levels),
+                field("spread", &mut p.spread),
+            ],
+        }
+    }
+
+    /// How each compute pass of the filter is dispatched, `None` dispatches all of them per pixel
+    pub(crate) fn compute_dispatches(&self) -> Option<Vec<PassDispatch>> {
+        match self {
+            Self::Dither(p) => Some(match p.method {
+                // 有序抖动在第一个 pass 中已经完成
+                DitherMethod::Bayer | DitherMethod::BlueNoise => {
+                    vec![PassDispatch::PerPixel, PassDispatch::Skip]
+                }
+                // 误差扩散逐行串行进行，只需要一个 workgroup
+                DitherMethod::FloydSteinberg | DitherMethod::Atkinson => {
+                    vec![PassDispatch::PerPixel, PassDispatch::SingleWorkgroup]
+                }
+            }),
+            _ => None,
+        }
+    }
+
+    /// Bytes written to the stage's params buffer
+    pub(crate) fn storage_data(&self) -> Vec<u8> {
+        match self {
+            Self::Original => vec![0; 4],
+            Self::AsciiArt(p) => bytemuck::bytes_of(p).to_vec(),
+            Self::CrossHatch(p) => bytemuck::bytes_of(p).to_vec(),
+            Self::EdgeDetection(p) => bytemuck::bytes_of(p).to_vec(),
+            Self::Lut3D(p) => bytemuck::bytes_of(p).to_vec(),
+            Self::ColorAdjust(p) => bytemuck::bytes_of(p).to_vec(),
+            Self::Halftone(p) => bytemuck::bytes_of(p).to_vec(),
+            Self::ToneCurve(p) => {
+                bytemuck::cast_slice(&[p.black_point, p.white_point, p.gamma]).to_vec()
+            }
+            Self::GaussianBlur(p) => {
+                let radius = p.radius.round().min(MAX_BLUR_RADIUS).max(1.0);
+                let sigma = if p.sigma > 0.0 {
+                    p.sigma
+                } else {
+                    (radius / 3.0).max(0.5)
+                };
+                let mut data = vec![radius, sigma];
+                data.extend(gaussian_weights(radius as usize, sigma));
+                bytemuck::cast_slice(&data).to_vec()
+            }
+            Self::Dither(p) => {
+                let colors =
+                    &p.palette.colors()[..p.palette.colors().len().min(MAX_PALETTE_COLORS)];
+                let spread = if p.spread > 0.0 {
+                    p.spread
+                } else if !colors.is_empty() {
+                    1.0 / (colors.len() as f32).sqrt()
+                } else {
+                    1.0 / (p.levels.round().max(2.0) - 1.0)
+                };
+                let header = [
+                    p.pixel_size,
+                    p.method as u32 as f32,
+                    p.bayer_size,
+                    p.levels,
+                    spread,
+                    colors.len() as f32,
+                ];
+                let mut data = bytemuck::cast_slice(&header).to_vec();
+                for [r, g, b] in colors {
+                    data.extend_from_slice(&u32::from_le_bytes([*r, *g, *b, 0]).to_le_bytes());
+                }
+                data
+            }
+            Self::Custom(data) => {
+                let len = data.len().min(MAX_PARAMS_LEN).max(1);
+                let mut data = data.clone();
+                data.resize(len, 0.0);
+                bytemuck::cast_slice(&data).to_vec()
+            }
+        }
+    }
+}
+
+// 一维高斯核的半边权重，weights[0] 是中心像素，归一化后整个核的权重和为 1
+fn gaussian_weights(radius: usize, sigma: f32) -> Vec<f32> {
+    let mut weights: Vec<f32> = (0..=radius)
+        .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+    for w in weights.iter_mut() {
+        *w /= sum;
+    }
+    weights
+}
@@ -0,0 +1,226 @@
+use crate::FilterType;
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamType {
+    Float,
+    Int,
+    /// 0.0 or 1.0
+    Bool,
+}
+
+/// One field of a filter's parameter struct, array fields are listed per element as `name[i]`
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ParamSchema {
+    pub name: &'static str,
+    #[serde(rename = "type")]
+    pub ty: ParamType,
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,
+    pub step: f32,
+    /// 0 is a meaningful value of the `input_param` knob instead of selecting the default
+    pub zero_is_valid: bool,
+}
+
+impl ParamSchema {
+    fn new(name: &'static str, ty: ParamType, range: (f32, f32), default: f32, step: f32) -> Self {
+        Self {
+            name,
+            ty,
+            min: range.0,
+            max: range.1,
+            default,
+            step,
+            zero_is_valid: false,
+        }
+    }
+
+    fn zero_is_valid(mut self) -> Self {
+        self.zero_is_valid = true;
+        self
+    }
+
+    fn boolean(name: &'static str) -> Self {
+        Self::new(name, ParamType::Bool, (0.0, 1.0), 0.0, 1.0)
+    }
+
+    pub fn clamp(&self, value: f32) -> f32 {
+        value.min(self.max).max(self.min)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FilterSchema {
+    /// `FilterType` discriminant, as used by the FFI `set_filter`
+    pub id: u32,
+    pub filter_type: FilterType,
+    /// Display name, or the registered name of a custom filter
+    pub name: String,
+    /// The parameter driven by the single `input_param` of `set_filter`/`change_filter_param`
+    pub input_param: Option<&'static str>,
+    pub params: Vec<ParamSchema>,
+}
+
+impl FilterSchema {
+    pub fn param(&self, name: &str) -> Option<&ParamSchema> {
+        self.params.iter().find(|p| p.name == name)
+    }
+
+    /// Schema of the `input_param` knob
+    pub fn input_param_schema(&self) -> Option<&ParamSchema> {
+        self.param(self.input_param?)
+    }
+}
+
+/// Schema of a filter's parameters; pixel sized defaults are scaled by `scale_factor`
+pub fn filter_schema(ty: FilterType, scale_factor: f32) -> FilterSchema {
+    use ParamType::*;
+    let (name, input_param, params) = match ty {
+        FilterType::Original => ("Original", None, vec![]),
+        FilterType::AsciiArt => (
+            "ASCII Art",
+            Some("cell_size"),
+            vec![ParamSchema::new(
+                "cell_size",
+                Float,
+                (8.0, 64.0),
+                8.0 * scale_factor,
+                1.0,
+            )],
+        ),
+        FilterType::CrossHatch => (
+            "Cross Hatch",
+            Some("density"),
+            vec![
+                ParamSchema::new("density", Float, (10.0, 64.0), 10.0 * scale_factor, 1.0),
+                ParamSchema::new("line_width", Float, (0.5, 8.0), 0.8 * scale_factor, 0.1),
+                ParamSchema::new("thresholds[0]", Float, (0.0, 1.0), 0.8, 0.01),
+                ParamSchema::new("thresholds[1]", Float, (0.0, 1.0), 0.6, 0.01),
+                ParamSchema::new("thresholds[2]", Float, (0.0, 1.0), 0.3, 0.01),
+                ParamSchema::new("thresholds[3]", Float, (0.0, 1.0), 0.15, 0.01),
+                ParamSchema::new("edge_noise_suppression", Float, (0.05, 0.33), 0.125, 0.01),
+                ParamSchema::boolean("opaque_background_color"),
+            ],
+        ),
+        FilterType::EdgeDetection => (
+            "Edge Detection",
+            Some("noise_suppression"),
+            vec![
+                ParamSchema::new("noise_suppression", Float, (0.05, 0.33), 0.05, 0.01),
+                ParamSchema::boolean("opaque_background_color"),
+            ],
+        ),
+        FilterType::GaussianBlur => (
+            "Gaussian Blur",
+            Some("radius"),
+            vec![
+                ParamSchema::new("radius", Int, (1.0, 32.0), 4.0, 1.0),
+                // 0 表示根据 radius 自动计算
+                ParamSchema::new("sigma", Float, (0.0, 16.0), 0.0, 0.1),
+            ],
+        ),
+        FilterType::Lut3D => (
+            "3D LUT",
+            Some("intensity"),
+            // 0 表示不应用 LUT
+            vec![ParamSchema::new("intensity", Float, (0.0, 1.0), 1.0, 0.01).zero_is_valid()],
+        ),
+        FilterType::ColorAdjust => (
+            "Color Adjust",
+            Some("exposure"),
+            vec![
+                ParamSchema::new("exposure", Float, (-4.0, 4.0), 0.0, 0.05).zero_is_valid(),
+                ParamSchema::new("contrast", Float, (-1.0, 1.0), 0.0, 0.01),
+                ParamSchema::new("saturation", Float, (-1.0, 1.0), 0.0, 0.01),
+                ParamSchema::new("vibrance", Float, (-1.0, 1.0), 0.0, 0.01),
+                ParamSchema::new("highlights", Float, (-2.0, 2.0), 0.0, 0.05),
+                ParamSchema::new("shadows", Float, (-2.0, 2.0), 0.0, 0.05),
+                ParamSchema::new("temperature", Float, (-1.0, 1.0), 0.0, 0.01),
+                ParamSchema::new("tint", Float, (-1.0, 1.0), 0.0, 0.01),
+                ParamSchema::new("gamma", Float, (0.2, 5.0), 1.0, 0.01),
+            ],
+        ),
+        // 曲线的控制点不是标量参数，通过 set_tone_curve 设置
+        FilterType::ToneCurve => (
+            "Tone Curve",
+            Some("gamma"),
+            vec![
+                ParamSchema::new("black_point", Float, (0.0, 1.0), 0.0, 0.01),
+                ParamSchema::new("white_point", Float, (0.0, 1.0), 1.0, 0.01),
+                ParamSchema::new("gamma", Float, (0.1, 10.0), 1.0, 0.01),
+            ],
+        ),
+        FilterType::Halftone => (
+            "Halftone",
+            Some("cell_size"),
+            vec![
+                ParamSchema::new("cell_size", Float, (4.0, 64.0), 8.0 * scale_factor, 1.0),
+                ParamSchema::boolean("cmyk"),
+                // 0: 圆点，1: 线条，2: 菱形
+                ParamSchema::new("dot_shape", Int, (0.0, 2.0), 0.0, 1.0),
+                ParamSchema::new("angle", Float, (0.0, 180.0), 45.0, 1.0),
+                ParamSchema::new("angles[0]", Float, (0.0, 180.0), 15.0, 1.0),
+                ParamSchema::new("angles[1]", Float, (0.0, 180.0), 75.0, 1.0),
+                ParamSchema::new("angles[2]", Float, (0.0, 180.0), 0.0, 1.0),
+                ParamSchema::new("angles[3]", Float, (0.0, 180.0), 45.0, 1.0),
+                ParamSchema::new("paper_color[0]", Float, (0.0, 1.0), 1.0, 0.01),
+                ParamSchema::new("paper_color[1]", Float, (0.0, 1.0), 1.0, 0.01),
+                ParamSchema::new("paper_color[2]", Float, (0.0, 1.0), 1.0, 0.01),
+                ParamSchema::new("ink_color[0]", Float, (0.0, 1.0), 0.0, 0.01),
+                ParamSchema::new("ink_color[1]", Float, (0.0, 1.0), 0.0, 0.01),
+                ParamSchema::new("ink_color[2]", Float, (0.0, 1.0), 0.0, 0.01),
+                ParamSchema::boolean("opaque_background_color"),
+            ],
+        ),
+        // 调色板不是标量参数，通过 set_dither_palette 设置
+        FilterType::Dither => (
+            "Dither",
+            Some("pixel_size"),
+            vec![
+                // 0: Bayer，1: 蓝噪声，2: Floyd-Steinberg，3: Atkinson
+                ParamSchema::new("method", Int, (0.0, 3.0), 0.0, 1.0),
+                ParamSchema::new("pixel_size", Int, (1.0, 32.0), 2.0 * scale_factor, 1.0),
+                ParamSchema::new("bayer_size", Int, (2.0, 8.0), 4.0, 2.0),
+                ParamSchema::new("levels", Int, (2.0, 16.0), 2.0, 1.0),
+                ParamSchema::new("spread", Float, (0.0, 1.0), 0.0, 0.01),
+            ],
+        ),
+        // 自定义滤镜的参数不做限制，范围只是给 UI 的建议
+        FilterType::Custom => (
+            "Custom",
+            Some("param"),
+            vec![
+                ParamSchema::new("param", Float, (0.0, 1.0), 0.0, 0.01),
+                ParamSchema::boolean("opaque_background_color"),
+            ],
+        ),
+    };
+    FilterSchema {
+        id: ty as u32,
+        filter_type: ty,
+        name: name.to_string(),
+        input_param,
+        params,
+    }
+}
+
+/// Schemas of the built-in filters, in `FilterType` order
+pub fn builtin_filter_schemas(scale_factor: f32) -> Vec<FilterSchema> {
+    [
+        FilterType::Original,
+        FilterType::AsciiArt,
+        FilterType::CrossHatch,
+        FilterType::EdgeDetection,
+        FilterType::GaussianBlur,
+        FilterType::Lut3D,
+        FilterType::ColorAdjust,
+        FilterType::ToneCurve,
+        FilterType::Halftone,
+        FilterType::Dither,
+    ]
+    .into_iter()
+    .map(|ty| filter_schema(ty, scale_factor))
+    .collect()
+}
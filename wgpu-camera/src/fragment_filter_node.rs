@@ -1,5 +1,5 @@
 use crate::display_node::DisplayNode;
-use app_surface::AppSurface;
+use crate::shader_manager::ShaderError;
 use bytemuck::Pod;
 use idroid::{
     geometry::Plane,
@@ -7,6 +7,7 @@ use idroid::{
     BufferObj,
 };
 use std::collections::HashMap;
+use std::rc::Rc;
 use wgpu::util::DeviceExt;
 use wgpu::{
     BindingType, Buffer, BufferBindingType, PipelineLayout, ShaderModule, ShaderStages, Texture,
@@ -15,43 +16,60 @@ use wgpu::{
 pub(crate) struct FragmentFilterNode {
     bind_groups: HashMap<String, wgpu::BindGroup>,
     display_node: DisplayNode,
+    // 滤镜额外采样的纹理，如 3D LUT
+    aux_texture: Option<(Texture, wgpu::TextureView)>,
 }
 
 impl FragmentFilterNode {
-    pub fn new(app_surface: &AppSurface, shader_module: &ShaderModule) -> Self {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        viewport_size: (f32, f32),
+        shader_module: &ShaderModule,
+        aux_texture: Option<wgpu::TextureViewDimension>,
+    ) -> Self {
         Self {
             bind_groups: HashMap::new(),
-            display_node: DisplayNode::new::<PosTex>(app_surface, shader_module),
+            display_node: DisplayNode::new::<PosTex>(
+                device,
+                format,
+                viewport_size,
+                shader_module,
+                aux_texture,
+            ),
+            aux_texture: None,
         }
     }
 }
 
 impl crate::FilterNode for FragmentFilterNode {
-    fn change_filter(&mut self, app_surface: &AppSurface, shader_module: &wgpu::ShaderModule) {
-        self.display_node.change_filter(app_surface, shader_module);
+    fn change_filter(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        shader_module: &wgpu::ShaderModule,
+    ) -> Result<(), ShaderError> {
+        self.display_node
+            .change_filter(device, format, shader_module)
     }
 
     fn update_viewport(&mut self, viewport: (f32, f32, f32, f32)) {
         self.display_node.viewport = viewport;
     }
 
-    fn update_sampler(&mut self, sampler: wgpu::Sampler) {
+    fn update_sampler(&mut self, sampler: Option<Rc<wgpu::Sampler>>) {
         self.display_node.update_sampler(sampler);
     }
 
     fn update_bind_group(
         &mut self,
-        app_surface: &AppSurface,
+        device: &wgpu::Device,
         mvp_buffer: &Buffer,
         params_buffer: &Buffer,
-        external_texture: &Texture,
-        external_tv: Option<wgpu::TextureView>,
+        texture_view: &wgpu::TextureView,
+        _size: (u32, u32),
         tex_key: String,
     ) {
-        let texture_view = match external_tv {
-            Some(tv) => tv,
-            None => external_texture.create_view(&wgpu::TextureViewDescriptor::default()),
-        };
         // let texture_view = external_texture.create_view(&wgpu::TextureViewDescriptor {
         //     label: None,
         //     format: Some(wgpu::TextureFormat::Rgba8Unorm),
@@ -64,15 +82,25 @@ impl crate::FilterNode for FragmentFilterNode {
         // });
 
         let bind_group = self.display_node.create_bind_group(
-            app_surface,
+            device,
             mvp_buffer,
             params_buffer,
-            &texture_view,
+            texture_view,
+            self.aux_texture.as_ref().map(|(_, view)| view),
         );
 
         self.bind_groups.insert(tex_key, bind_group);
     }
 
+    fn set_aux_texture(&mut self, texture: Texture) {
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.aux_texture = Some((texture, view));
+    }
+
+    fn aux_texture(&self) -> Option<&Texture> {
+        self.aux_texture.as_ref().map(|(texture, _)| texture)
+    }
+
     fn remove_bind_group(&mut self, tex_key: String) {
         self.bind_groups.remove(&tex_key);
     }
@@ -90,4 +118,10 @@ impl crate::FilterNode for FragmentFilterNode {
         self.display_node
             .begin_render_pass(frame_view, encoder, bind_group)
     }
+
+    fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>, tex_key: &str) {
+        if let Some(bind_group) = self.bind_groups.get(tex_key) {
+            self.display_node.draw(rpass, bind_group);
+        }
+    }
 }
@@ -0,0 +1,231 @@
+use crate::filter_chain::{FilterStage, SourceTexture};
+use crate::layout::{ContentLayout, ContentMode, DisplayUniform, Mirror};
+use crate::{FilterParams, FilterType};
+use idroid::BufferObj;
+use nalgebra_glm as glm;
+use std::rc::Rc;
+
+/// A texture drawn with its own filter into a rectangle of the surface, see
+/// `WgpuCanvas::set_layers`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    /// Key the texture was registered with by `set_external_texture`
+    pub tex_key: String,
+    /// Filter of the layer and its parameters, custom filters are not supported
+    pub params: FilterParams,
+    /// Normalized `(x, y, width, height)` of the surface, `(0, 0)` is its top left corner
+    pub frame: (f32, f32, f32, f32),
+    /// Layers with a higher z index are drawn on top of the others
+    pub z_index: i32,
+    /// How the texture is fitted into `frame`
+    pub content_mode: ContentMode,
+    pub mirror: Mirror,
+}
+
+impl Layer {
+    pub fn new(tex_key: &str, filter: FilterType, frame: (f32, f32, f32, f32)) -> Self {
+        Self {
+            tex_key: tex_key.to_string(),
+            params: FilterParams::default_for(filter),
+            frame,
+            z_index: 0,
+            content_mode: ContentMode::default(),
+            mirror: Mirror::NONE,
+        }
+    }
+
+    /// `tex_key` with each of `filters`, laid out row by row in a grid of `columns` columns
+    pub fn grid(tex_key: &str, filters: &[FilterType], columns: usize) -> Vec<Self> {
+        let columns = columns.max(1);
+        let rows = (filters.len() + columns - 1) / columns;
+        let (w, h) = (1.0 / columns as f32, 1.0 / rows.max(1) as f32);
+        filters
+            .iter()
+            .enumerate()
+            .map(|(i, filter)| {
+                let (column, row) = ((i % columns) as f32, (i / columns) as f32);
+                Self::new(tex_key, *filter, (column * w, row * h, w, h))
+            })
+            .collect()
+    }
+}
+
+struct LayerStage {
+    layer: Layer,
+    stage: FilterStage,
+    // 每个图层有自己的镜像与 viewport，mvp 不能共享
+    mvp_buf: BufferObj,
+    // 已绑定的源纹理尺寸，未绑定的图层不绘制
+    source_size: Option<(u32, u32)>,
+}
+
+/// Draws every layer in a single render pass, in z order
+pub(crate) struct LayerCompositor {
+    layers: Vec<LayerStage>,
+    // 图层直接绘制到 surface 上
+    target_srgb: bool,
+}
+
+impl LayerCompositor {
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        Self {
+            layers: vec![],
+            target_srgb: format.describe().srgb,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    pub fn layers(&self) -> impl Iterator<Item = &Layer> {
+        self.layers.iter().map(|entry| &entry.layer)
+    }
+
+    /// Remove every layer, their stages are returned so that their pipelines can be reused
+    pub fn take_stages(&mut self) -> Vec<FilterStage> {
+        self.layers
+            .drain(..)
+            .map(|mut entry| {
+                entry
+                    .stage
+                    .node
+                    .remove_bind_group(entry.layer.tex_key.clone());
+                entry.stage
+            })
+            .collect()
+    }
+
+    pub fn push(&mut self, device: &wgpu::Device, layer: Layer, stage: FilterStage) {
+        let mvp_buf = BufferObj::create_uniform_buffer(
+            device,
+            &DisplayUniform::new(glm::Mat4::identity().into()),
+            Some("layer mvp"),
+        );
+        self.layers.push(LayerStage {
+            layer,
+            stage,
+            mvp_buf,
+            source_size: None,
+        });
+    }
+
+    /// Bind `source` to the layers that draw `tex_key`
+    pub fn bind_source(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        tex_key: &str,
+        source: &SourceTexture,
+        external_sampler: Option<Rc<wgpu::Sampler>>,
+    ) {
+        for entry in self
+            .layers
+            .iter_mut()
+            .filter(|entry| entry.layer.tex_key == tex_key)
+        {
+            entry.stage.node.update_sampler(external_sampler.clone());
+            entry.stage.set_srgb(queue, source.srgb, self.target_srgb);
+            entry.stage.node.update_bind_group(
+                device,
+                &entry.mvp_buf.buffer,
+                &entry.stage.params_buffer.buffer,
+                &source.view,
+                source.size,
+                tex_key.to_string(),
+            );
+            entry.source_size = Some(source.size);
+        }
+    }
+
+    pub fn remove_source(&mut self, tex_key: &str) {
+        for entry in self
+            .layers
+            .iter_mut()
+            .filter(|entry| entry.layer.tex_key == tex_key)
+        {
+            entry.stage.node.remove_bind_group(tex_key.to_string());
+            entry.source_size = None;
+        }
+    }
+
+    /// `view_proj` maps the full screen plane onto the viewport, `rotation` is the clockwise
+    /// rotation of the textures in degrees
+    pub fn enter_frame(
+        &mut self,
+        queue: &wgpu::Queue,
+        frame_view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+        view_proj: glm::Mat4,
+        rotation: f32,
+        surface_size: (u32, u32),
+    ) {
+        let (sw, sh) = (surface_size.0 as f32, surface_size.1 as f32);
+        // sort_by_key 是稳定排序，z index 相同的图层按添加顺序绘制
+        let mut order: Vec<usize> = (0..self.layers.len())
+            .filter(|&i| self.layers[i].source_size.is_some())
+            .collect();
+        order.sort_by_key(|&i| self.layers[i].layer.z_index);
+
+        let mut scissors = vec![];
+        for &i in order.iter() {
+            let entry = &mut self.layers[i];
+            let size = entry.source_size.unwrap();
+            let (x, y, w, h) = entry.layer.frame;
+            let (fx, fy, fw, fh) = (x * sw, y * sh, w * sw, h * sh);
+            let layout = ContentLayout::new(
+                entry.layer.content_mode,
+                None,
+                (size.0 as f32, size.1 as f32),
+                rotation,
+                (fw, fh),
+            );
+            let (vx, vy, vw, vh) = layout.viewport;
+            entry.stage.node.update_viewport((fx + vx, fy + vy, vw, vh));
+
+            let (mx, my) = entry.layer.mirror.scale();
+            let model = glm::scaling(&glm::vec3(mx, my, 1.0))
+                * glm::rotation(
+                    rotation / 180.0 * (-std::f32::consts::PI),
+                    &glm::vec3(0.0, 0.0, 1.0),
+                );
+            let uniform = DisplayUniform::new((view_proj * model).into());
+            queue.write_buffer(&entry.mvp_buf.buffer, 0, bytemuck::bytes_of(&uniform));
+            // compute 滤镜需要在 render pass 之前执行
+            entry.stage.node.prepare(encoder, &entry.layer.tex_key);
+
+            // AspectFill 时 viewport 会超出图层的区域，用 scissor 裁掉
+            let left = fx.max(0.0).min(sw) as u32;
+            let top = fy.max(0.0).min(sh) as u32;
+            let right = (fx + fw).max(0.0).min(sw) as u32;
+            let bottom = (fy + fh).max(0.0).min(sh) as u32;
+            scissors.push((
+                left,
+                top,
+                right.saturating_sub(left),
+                bottom.saturating_sub(top),
+            ));
+        }
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("layers pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: frame_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        for (&i, &(x, y, w, h)) in order.iter().zip(scissors.iter()) {
+            if w == 0 || h == 0 {
+                continue;
+            }
+            let entry = &self.layers[i];
+            rpass.set_scissor_rect(x, y, w, h);
+            entry.stage.node.draw(&mut rpass, &entry.layer.tex_key);
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use bytemuck::{Pod, Zeroable};
+use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
+
+/// How the source image is fitted into the surface
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ContentMode {
+    /// Fill the whole surface, the image overflows it on one axis
+    #[default]
+    AspectFill,
+    /// Fit the whole image inside the surface, letterboxed on one axis
+    AspectFit,
+    /// Fill the surface, ignoring the aspect ratio of the image
+    Stretch,
+}
+
+/// A normalized rectangle of the source texture, `(0, 0)` is its top left corner
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CropRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl CropRect {
+    pub const FULL: Self = Self {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Clamped into the texture, `None` if nothing of it is left
+    pub fn clamped(&self) -> Option<Self> {
+        let x = self.x.max(0.0).min(1.0);
+        let y = self.y.max(0.0).min(1.0);
+        let width = (self.x + self.width).min(1.0) - x;
+        let height = (self.y + self.height).min(1.0) - y;
+        if width > 0.0 && height > 0.0 {
+            Some(Self::new(x, y, width, height))
+        } else {
+            None
+        }
+    }
+}
+
+/// `UniformData` of `common/group0+vs.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub(crate) struct DisplayUniform {
+    pub mvp_matrix: [[f32; 4]; 4],
+    pub uv_offset: [f32; 2],
+    pub uv_scale: [f32; 2],
+}
+
+impl DisplayUniform {
+    pub fn new(mvp_matrix: [[f32; 4]; 4]) -> Self {
+        Self {
+            mvp_matrix,
+            uv_offset: [0.0, 0.0],
+            uv_scale: [1.0, 1.0],
+        }
+    }
+}
+
+// mvp_matrix 之后是 uv 变换，可以单独更新
+pub(crate) const UV_TRANSFORM_OFFSET: wgpu::BufferAddress = 64;
+
+/// Viewport and source texture-coordinate transform of the stage drawn to the surface
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ContentLayout {
+    pub viewport: (f32, f32, f32, f32),
+    pub uv_offset: [f32; 2],
+    pub uv_scale: [f32; 2],
+}
+
+impl ContentLayout {
+    /// `rotation` is the clockwise rotation of the image on the surface, in degrees
+    pub fn new(
+        mode: ContentMode,
+        crop: Option<CropRect>,
+        img_size: (f32, f32),
+        rotation: f32,
+        surface_size: (f32, f32),
+    ) -> Self {
+        let crop = crop
+            .and_then(|crop| crop.clamped())
+            .unwrap_or(CropRect::FULL);
+        let (sw, sh) = surface_size;
+        // 裁剪并旋转后实际显示的图像尺寸
+        let (w, h) = rotated_size(
+            (img_size.0 * crop.width, img_size.1 * crop.height),
+            rotation,
+        );
+        let viewport = if w <= 0.0 || h <= 0.0 || mode == ContentMode::Stretch {
+            (0.0, 0.0, sw, sh)
+        } else {
+            let w_ratio = sw / w;
+            let h_ratio = sh / h;
+            let ratio = match mode {
+                ContentMode::AspectFill => w_ratio.max(h_ratio),
+                _ => w_ratio.min(h_ratio),
+            };
+            let (vw, vh) = (w * ratio, h * ratio);
+            ((sw - vw) / 2.0, (sh - vh) / 2.0, vw, vh)
+        };
+        Self {
+            viewport,
+            uv_offset: [crop.x, crop.y],
+            uv_scale: [crop.width, crop.height],
+        }
+    }
+
+    pub fn uv_transform(&self) -> [f32; 4] {
+        [
+            self.uv_offset[0],
+            self.uv_offset[1],
+            self.uv_scale[0],
+            self.uv_scale[1],
+        ]
+    }
+}
+
+/// Size of an image of `size` once rotated by `rotation` degrees, width and height swap for
+/// 90 and 270 degrees (rounded to the nearest quarter turn)
+pub(crate) fn rotated_size<T>(size: (T, T), rotation: f32) -> (T, T) {
+    let quarter_turns = (rotation / 90.0).round() as i32;
+    if quarter_turns.rem_euclid(2) == 1 {
+        (size.1, size.0)
+    } else {
+        size
+    }
+}
+
+/// Mirroring of the image, applied after the camera sensor orientation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Mirror {
+    /// Flip left and right, as a front-facing camera preview usually is
+    pub horizontal: bool,
+    /// Flip top and bottom
+    pub vertical: bool,
+}
+
+impl Mirror {
+    pub const NONE: Self = Self {
+        horizontal: false,
+        vertical: false,
+    };
+
+    pub fn new(horizontal: bool, vertical: bool) -> Self {
+        Self {
+            horizontal,
+            vertical,
+        }
+    }
+
+    /// Scale of the model x and y axes, mirroring is its own inverse
+    pub(crate) fn scale(&self) -> (f32, f32) {
+        (
+            if self.horizontal { -1.0 } else { 1.0 },
+            if self.vertical { -1.0 } else { 1.0 },
+        )
+    }
+}
+
+/// Digital zoom, pan and rotation of the preview, applied on top of the content mode and the
+/// camera sensor orientation
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ViewTransform {
+    /// 1.0 shows the image as laid out by the content mode
+    pub scale: f32,
+    /// Offset of the image center, in surface pixels (y down)
+    pub translate: (f32, f32),
+    /// Clockwise, in degrees
+    pub rotation: f32,
+}
+
+impl Default for ViewTransform {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            translate: (0.0, 0.0),
+            rotation: 0.0,
+        }
+    }
+}
+
+impl ViewTransform {
+    /// In `AspectFill` and `Stretch` modes the transformed image must still cover the whole
+    /// surface, so the scale is raised and the translation limited as needed
+    pub(crate) fn clamped(
+        &self,
+        mode: ContentMode,
+        viewport: (f32, f32, f32, f32),
+        surface_size: (f32, f32),
+    ) -> Self {
+        let mut transform = *self;
+        transform.scale = transform.scale.max(0.01);
+        let (a, b) = (viewport.2 / 2.0, viewport.3 / 2.0);
+        if mode == ContentMode::AspectFit || a <= 0.0 || b <= 0.0 {
+            return transform;
+        }
+        // 在随图像旋转的坐标系中，surface 的半宽高
+        let (sin, cos) = (-self.rotation.to_radians()).sin_cos();
+        let (hw, hh) = (surface_size.0 / 2.0, surface_size.1 / 2.0);
+        let mx = hw * cos.abs() + hh * sin.abs();
+        let my = hw * sin.abs() + hh * cos.abs();
+        transform.scale = transform.scale.max(mx / a).max(my / b);
+
+        // 每个轴上的平移范围在旋转后的坐标系中是独立的
+        let (tx, ty) = (transform.translate.0, -transform.translate.1);
+        let ux = (tx * cos + ty * sin).clamp(-(a * transform.scale - mx), a * transform.scale - mx);
+        let uy =
+            (-tx * sin + ty * cos).clamp(-(b * transform.scale - my), b * transform.scale - my);
+        transform.translate = (ux * cos - uy * sin, -(ux * sin + uy * cos));
+        transform
+    }
+
+    /// The transform in the model space of the full screen plane, where `[-1, 1]` spans
+    /// `viewport`
+    pub(crate) fn model_matrix(&self, viewport: (f32, f32, f32, f32)) -> glm::Mat4 {
+        let (a, b) = (viewport.2 / 2.0, viewport.3 / 2.0);
+        if a <= 0.0 || b <= 0.0 {
+            return glm::Mat4::identity();
+        }
+        // 在像素空间中缩放、旋转与平移，旋转才不会因图像宽高比而变形
+        glm::scaling(&glm::vec3(1.0 / a, 1.0 / b, 1.0))
+            * glm::translation(&glm::vec3(self.translate.0, -self.translate.1, 0.0))
+            * glm::rotation(-self.rotation.to_radians(), &glm::vec3(0.0, 0.0, 1.0))
+            * glm::scaling(&glm::vec3(self.scale, self.scale, 1.0))
+            * glm::scaling(&glm::vec3(a, b, 1.0))
+    }
+
+    /// Map a position in surface pixels (y down) into the `[-1, 1]` model space of `viewport`,
+    /// the inverse of `model_matrix` followed by the viewport transform
+    pub(crate) fn to_model(
+        &self,
+        pos: (f32, f32),
+        viewport: (f32, f32, f32, f32),
+    ) -> Option<(f32, f32)> {
+        let (a, b) = (viewport.2 / 2.0, viewport.3 / 2.0);
+        if a <= 0.0 || b <= 0.0 {
+            return None;
+        }
+        // 以 viewport 中心为原点、y 轴向上的像素坐标
+        let cx = viewport.0 + a;
+        let cy = viewport.1 + b;
+        let px = pos.0 - cx - self.translate.0;
+        let py = -(pos.1 - cy - self.translate.1);
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let qx = (px * cos - py * sin) / self.scale;
+        let qy = (px * sin + py * cos) / self.scale;
+        Some((qx / a, qy / b))
+    }
+}
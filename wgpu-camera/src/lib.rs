@@ -1,7 +1,32 @@
-use app_surface::AppSurface;
-use wgpu::{Buffer, Texture};
+use std::rc::Rc;
+use wgpu::Buffer;
+mod canvas_target;
+pub use canvas_target::{CanvasTarget, OffscreenTarget};
+mod compute_node;
+pub use compute_node::{ComputeBindings, ComputeLayout, ComputeNode, ComputeNodeBuilder};
 mod wgpu_canvas;
 pub use wgpu_canvas::WgpuCanvas;
+mod wgsl_preprocessor;
+pub use filter_params::{
+    AsciiArtParams, ColorAdjustParams, CrossHatchParams, DitherMethod, DitherParams,
+    EdgeDetectionParams, FilterParams, GaussianBlurParams, HalftoneParams, Lut3DParams,
+    ToneCurveParams, MAX_PALETTE_COLORS,
+};
+pub use filter_schema::{
+    builtin_filter_schemas, filter_schema, FilterSchema, ParamSchema, ParamType,
+};
+pub use layer_compositor::Layer;
+pub use layout::{ContentMode, CropRect, Mirror, ViewTransform};
+pub use lut::{Lut3D, LutError, MAX_LUT_SIZE};
+pub use palette::{Palette, PaletteError};
+pub use preset::{
+    CustomStagePreset, LayoutPreset, Preset, PresetError, StagePreset, PRESET_VERSION,
+};
+pub use shader_manager::ShaderError;
+pub use tone_curve::{CurveChannel, ToneCurve};
+pub use wgsl_preprocessor::{
+    PreprocessError, PreprocessedShader, SourceLocation, WgslPreprocessor,
+};
 
 #[cfg_attr(target_os = "ios", path = "ffi/ios.rs")]
 #[cfg_attr(
@@ -13,27 +38,133 @@ mod ffi;
 pub use ffi::*;
 
 #[repr(C)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum FilterType {
     Original,
     AsciiArt,
     CrossHatch,
     EdgeDetection,
+    GaussianBlur,
+    /// A fragment shader registered with `WgpuCanvas::register_filter`, selected by name with
+    /// `WgpuCanvas::set_custom_filter`
+    Custom,
+    /// Color grading with a 3D LUT loaded by `WgpuCanvas::set_lut`
+    Lut3D,
+    /// Exposure, contrast, saturation, white balance and gamma, computed in linear light
+    ColorAdjust,
+    /// Levels followed by per channel and master curves, see `WgpuCanvas::set_tone_curve`
+    ToneCurve,
+    /// Print screen of dots, in a single ink or CMYK separations
+    Halftone,
+    /// Ordered or error diffusion dithering to a palette, with chunky pixels
+    Dither,
+}
+
+impl FilterType {
+    /// Filters implemented as compute passes, displayed through `ComputeFilterNode`
+    pub fn is_compute(&self) -> bool {
+        match self {
+            FilterType::Original
+            | FilterType::AsciiArt
+            | FilterType::CrossHatch
+            | FilterType::EdgeDetection
+            | FilterType::Custom
+            | FilterType::Lut3D
+            | FilterType::ColorAdjust
+            | FilterType::ToneCurve
+            | FilterType::Halftone => false,
+            FilterType::GaussianBlur | FilterType::Dither => true,
+        }
+    }
+
+    /// Rows of `vec4<f32>` scratch storage, as wide as the source texture, that the compute
+    /// passes of a filter share
+    pub(crate) fn compute_scratch_rows(&self) -> Option<u32> {
+        match self {
+            // 误差扩散的环形缓冲区
+            FilterType::Dither => Some(shader_defines::DITHER_ERROR_ROWS),
+            _ => None,
+        }
+    }
+
+    /// Dimension of the extra texture a filter samples at binding 4
+    pub(crate) fn aux_texture_dimension(&self) -> Option<wgpu::TextureViewDimension> {
+        match self {
+            FilterType::Lut3D => Some(wgpu::TextureViewDimension::D3),
+            FilterType::ToneCurve => Some(wgpu::TextureViewDimension::D2),
+            _ => None,
+        }
+    }
+}
+
+/// How `WgpuCanvas::set_filter_animated` blends the old filter into the new one
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    Crossfade,
+    /// The new filter sweeps in from the right edge towards the left
+    WipeLeft,
+    WipeRight,
+    /// The new filter sweeps in from the bottom edge towards the top
+    WipeUp,
+    WipeDown,
+    /// The new filter is revealed by a circle growing from the center
+    CircularReveal,
+    /// The old filter is curled away like a page from the bottom right corner, the page can
+    /// also follow a touch, see `WgpuCanvas::begin_page_curl`
+    PageCurl,
+}
+
+/// Orientation of the divider of `WgpuCanvas::set_comparison`, the original image is on its
+/// left, top or top left side
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareDivider {
+    Vertical,
+    Horizontal,
+    /// Perpendicular to the diagonal from the top left to the bottom right corner
+    Diagonal,
+}
+
+impl CompareDivider {
+    // 与 uv 的点积在分割线移动的方向上从 0 变到 1
+    pub(crate) fn normal(&self) -> [f32; 2] {
+        match self {
+            Self::Vertical => [1.0, 0.0],
+            Self::Horizontal => [0.0, 1.0],
+            Self::Diagonal => [0.5, 0.5],
+        }
+    }
 }
 
 pub(crate) trait FilterNode {
-    fn change_filter(&mut self, app_surface: &AppSurface, shader_module: &wgpu::ShaderModule);
+    /// Replace the shader of a fragment filter, the node keeps its current pipeline if wgpu
+    /// rejects the new one
+    fn change_filter(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        shader_module: &wgpu::ShaderModule,
+    ) -> Result<(), ShaderError>;
     fn update_viewport(&mut self, viewport: (f32, f32, f32, f32));
-    fn update_sampler(&mut self, _sampler: wgpu::Sampler) {}
+    fn update_sampler(&mut self, _sampler: Option<Rc<wgpu::Sampler>>) {}
     fn update_bind_group(
         &mut self,
-        app_surface: &AppSurface,
+        device: &wgpu::Device,
         mvp_buffer: &Buffer,
         params_buffer: &Buffer,
-        external_texture: &Texture,
-        external_tv: Option<wgpu::TextureView>,
+        texture_view: &wgpu::TextureView,
+        size: (u32, u32),
         tex_key: String,
     );
+    /// Called with the params of the stage whenever they change
+    fn update_params(&mut self, _params: &FilterParams) {}
+    /// Replace the texture at binding 4 of a filter that samples one, the bind groups have to
+    /// be updated afterwards
+    fn set_aux_texture(&mut self, _texture: wgpu::Texture) {}
+    fn aux_texture(&self) -> Option<&wgpu::Texture> {
+        None
+    }
     fn remove_bind_group(&mut self, tex_key: String);
     fn enter_frame(
         &mut self,
@@ -41,12 +172,35 @@ pub(crate) trait FilterNode {
         encoder: &mut wgpu::CommandEncoder,
         tex_key: String,
     );
+    /// Work that has to be encoded before the render pass of `draw`, such as a compute pass
+    fn prepare(&mut self, _encoder: &mut wgpu::CommandEncoder, _tex_key: &str) {}
+    /// Draw `tex_key` into a render pass shared with other nodes, at the node's viewport
+    fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>, tex_key: &str);
 }
 
 mod compute_filter_node;
 mod display_node;
+mod filter_chain;
+mod filter_params;
+mod filter_schema;
 mod fragment_filter_node;
+mod layer_compositor;
+mod layout;
+mod lut;
+mod page_turning;
+mod palette;
+mod preset;
+mod readback;
+mod shader_defines;
 mod shader_manager;
+#[cfg(all(
+    feature = "hot-reload",
+    not(any(target_os = "ios", target_os = "android", target_arch = "wasm32"))
+))]
+mod shader_watcher;
+mod texture_pool;
+mod tone_curve;
+mod transition_node;
 
 #[repr(C)]
 pub struct ExternalTextureObj {
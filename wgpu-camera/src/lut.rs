@@ -0,0 +1,465 @@
+/// Largest supported edge length of a 3D LUT, a level 8 Hald CLUT has 64
+pub const MAX_LUT_SIZE: usize = 65;
+
+/// Why a `.cube` file or Hald CLUT image could not be loaded
+#[derive(Debug)]
+pub enum LutError {
+    /// A line of a `.cube` file that could not be parsed, `line` starts at 1
+    Parse {
+        line: usize,
+        message: String,
+    },
+    /// The `.cube` file has no `LUT_3D_SIZE`
+    MissingSize,
+    /// The edge length is below 2 or above `MAX_LUT_SIZE`
+    UnsupportedSize(usize),
+    /// The number of table entries does not match the size
+    EntryCount {
+        expected: usize,
+        found: usize,
+    },
+    /// `DOMAIN_MAX` is not above `DOMAIN_MIN` on every channel
+    InvalidDomain,
+    Png(png::DecodingError),
+    /// A Hald CLUT image must be a square of `level³` pixels
+    InvalidHaldSize {
+        width: u32,
+        height: u32,
+    },
+    /// A Hald CLUT PNG must be RGB or RGBA
+    UnsupportedColorType(png::ColorType),
+}
+
+impl std::fmt::Display for LutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse { line, message } => write!(f, "invalid .cube line {}: {}", line, message),
+            Self::MissingSize => f.write_str("missing LUT_3D_SIZE"),
+            Self::UnsupportedSize(size) => write!(
+                f,
+                "unsupported LUT size {}, supported sizes are 2 to {}",
+                size, MAX_LUT_SIZE
+            ),
+            Self::EntryCount { expected, found } => {
+                write!(f, "expected {} LUT entries, found {}", expected, found)
+            }
+            Self::InvalidDomain => f.write_str("DOMAIN_MAX must be greater than DOMAIN_MIN"),
+            Self::Png(e) => write!(f, "invalid Hald CLUT png: {}", e),
+            Self::InvalidHaldSize { width, height } => write!(
+                f,
+                "a Hald CLUT must be a square of level³ pixels, found {}x{}",
+                width, height
+            ),
+            Self::UnsupportedColorType(ty) => {
+                write!(f, "unsupported Hald CLUT color type {:?}", ty)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LutError {}
+
+impl From<png::DecodingError> for LutError {
+    fn from(e: png::DecodingError) -> Self {
+        Self::Png(e)
+    }
+}
+
+/// A 3D color lookup table for `FilterType::Lut3D`, set with `WgpuCanvas::set_lut`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lut3D {
+    size: usize,
+    // 红色通道变化最快，然后是绿色、蓝色，与 .cube 文件及 3D 纹理的布局一致
+    data: Vec<[f32; 3]>,
+}
+
+impl Lut3D {
+    /// A LUT that leaves every color unchanged
+    pub fn identity(size: usize) -> Self {
+        let size = size.clamp(2, MAX_LUT_SIZE);
+        let max = (size - 1) as f32;
+        let mut data = Vec::with_capacity(size * size * size);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    data.push([r as f32 / max, g as f32 / max, b as f32 / max]);
+                }
+            }
+        }
+        Self { size, data }
+    }
+
+    /// Parse an Adobe/Resolve `.cube` file
+    ///
+    /// Inputs outside of `DOMAIN_MIN`..`DOMAIN_MAX` are clamped to it; a domain other than
+    /// `0..1` is resampled to `0..1`, the range of the colors the filter receives.
+    pub fn from_cube(text: &str) -> Result<Self, LutError> {
+        let mut size = None;
+        let mut domain_min = [0.0_f32; 3];
+        let mut domain_max = [1.0_f32; 3];
+        let mut data = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let line_number = i + 1;
+            let parse_error = |message: &str| LutError::Parse {
+                line: line_number,
+                message: message.to_string(),
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap();
+            match keyword {
+                "TITLE" => {}
+                "LUT_1D_SIZE" => return Err(parse_error("1D LUTs are not supported")),
+                "LUT_3D_SIZE" => {
+                    let value = words
+                        .next()
+                        .and_then(|word| word.parse::<usize>().ok())
+                        .ok_or_else(|| parse_error("LUT_3D_SIZE must be an integer"))?;
+                    if !(2..=MAX_LUT_SIZE).contains(&value) {
+                        return Err(LutError::UnsupportedSize(value));
+                    }
+                    size = Some(value);
+                }
+                "DOMAIN_MIN" | "DOMAIN_MAX" => {
+                    let values = parse_triple(words).ok_or_else(|| {
+                        parse_error(&format!("{} must have three numbers", keyword))
+                    })?;
+                    if keyword == "DOMAIN_MIN" {
+                        domain_min = values;
+                    } else {
+                        domain_max = values;
+                    }
+                }
+                _ if keyword.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.') => {
+                    let values = parse_triple(line.split_whitespace())
+                        .ok_or_else(|| parse_error("a table entry must have three numbers"))?;
+                    data.push(values);
+                }
+                // 其他软件写入的未知关键字
+                _ => log::warn!("ignoring .cube keyword {} on line {}", keyword, line_number),
+            }
+        }
+        let size = size.ok_or(LutError::MissingSize)?;
+        let expected = size * size * size;
+        if data.len() != expected {
+            return Err(LutError::EntryCount {
+                expected,
+                found: data.len(),
+            });
+        }
+        if (0..3).any(|c| domain_max[c] <= domain_min[c]) {
+            return Err(LutError::InvalidDomain);
+        }
+        let lut = Self { size, data };
+        if domain_min == [0.0; 3] && domain_max == [1.0; 3] {
+            Ok(lut)
+        } else {
+            Ok(lut.resample_domain(domain_min, domain_max))
+        }
+    }
+
+    /// Decode a Hald CLUT PNG, a square image of `level³` pixels describing a LUT of size
+    /// `level²`
+    pub fn from_hald_png(bytes: &[u8]) -> Result<Self, LutError> {
+        let mut decoder = png::Decoder::new(std::io::Cursor::new(bytes));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        let channels = match info.color_type {
+            png::ColorType::Rgb => 3,
+            png::ColorType::Rgba => 4,
+            ty => return Err(LutError::UnsupportedColorType(ty)),
+        };
+        let pixels = buf[..info.buffer_size()]
+            .chunks_exact(channels)
+            .map(|p| [p[0], p[1], p[2]]);
+        Self::from_hald_pixels(info.width, info.height, pixels)
+    }
+
+    /// A Hald CLUT that has already been decoded, e.g. by the `image` crate
+    pub fn from_hald_image(image: &image::RgbaImage) -> Result<Self, LutError> {
+        let pixels = image.pixels().map(|p| [p[0], p[1], p[2]]);
+        Self::from_hald_pixels(image.width(), image.height(), pixels)
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    fn from_hald_pixels(
+        width: u32,
+        height: u32,
+        pixels: impl Iterator<Item = [u8; 3]>,
+    ) -> Result<Self, LutError> {
+        let level = (width as f64).cbrt().round() as usize;
+        if width != height || level * level * level != width as usize {
+            return Err(LutError::InvalidHaldSize { width, height });
+        }
+        let size = level * level;
+        if !(2..=MAX_LUT_SIZE).contains(&size) {
+            return Err(LutError::UnsupportedSize(size));
+        }
+        // 按行读取的像素顺序与 LUT 的顺序相同，红色变化最快
+        let data: Vec<[f32; 3]> = pixels
+            .map(|p| {
+                [
+                    p[0] as f32 / 255.0,
+                    p[1] as f32 / 255.0,
+                    p[2] as f32 / 255.0,
+                ]
+            })
+            .collect();
+        Ok(Self { size, data })
+    }
+
+    // 在 0..1 的格点上重新采样，使 shader 不需要关心 domain
+    fn resample_domain(&self, domain_min: [f32; 3], domain_max: [f32; 3]) -> Self {
+        let max = (self.size - 1) as f32;
+        let mut data = Vec::with_capacity(self.data.len());
+        for b in 0..self.size {
+            for g in 0..self.size {
+                for r in 0..self.size {
+                    let color = [r as f32 / max, g as f32 / max, b as f32 / max];
+                    let mut coord = [0.0; 3];
+                    for c in 0..3 {
+                        let t = (color[c] - domain_min[c]) / (domain_max[c] - domain_min[c]);
+                        coord[c] = t.clamp(0.0, 1.0) * max;
+                    }
+                    data.push(self.sample(coord));
+                }
+            }
+        }
+        Self {
+            size: self.size,
+            data,
+        }
+    }
+
+    // 三线性插值，coord 以格点为单位
+    fn sample(&self, coord: [f32; 3]) -> [f32; 3] {
+        let n = self.size;
+        let index = |r: usize, g: usize, b: usize| r + g * n + b * n * n;
+        let lo = coord.map(|c| (c.floor() as usize).min(n - 2));
+        let t = [
+            coord[0] - lo[0] as f32,
+            coord[1] - lo[1] as f32,
+            coord[2] - lo[2] as f32,
+        ];
+        let mut result = [0.0; 3];
+        for corner in 0..8 {
+            let (dr, dg, db) = (corner & 1, (corner >> 1) & 1, (corner >> 2) & 1);
+            let weight = (if dr == 1 { t[0] } else { 1.0 - t[0] })
+                * (if dg == 1 { t[1] } else { 1.0 - t[1] })
+                * (if db == 1 { t[2] } else { 1.0 - t[2] });
+            let value = self.data[index(lo[0] + dr, lo[1] + dg, lo[2] + db)];
+            for c in 0..3 {
+                result[c] += value[c] * weight;
+            }
+        }
+        result
+    }
+
+    /// Upload as an `Rgba16Float` 3D texture, sampled with trilinear filtering by `lut3d.wgsl`
+    ///
+    /// 8 bits per entry would band the smooth gradients that a 33³ or 65³ LUT describes.
+    pub(crate) fn create_texture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> wgpu::Texture {
+        let n = self.size as u32;
+        let size = wgpu::Extent3d {
+            width: n,
+            height: n,
+            depth_or_array_layers: n,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("lut3d texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format: wgpu::TextureFormat::Rgba16Float,
+            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let texels: Vec<u16> = self
+            .data
+            .iter()
+            .flat_map(|color| {
+                let [r, g, b] = color.map(|c| f16_bits(c.clamp(0.0, 1.0)));
+                [r, g, b, f16_bits(1.0)]
+            })
+            .collect();
+        queue.write_texture(
+            texture.as_image_copy(),
+            bytemuck::cast_slice(&texels),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(n * 8),
+                rows_per_image: std::num::NonZeroU32::new(n),
+            },
+            size,
+        );
+        texture
+    }
+}
+
+fn parse_triple<'a>(mut words: impl Iterator<Item = &'a str>) -> Option<[f32; 3]> {
+    let mut values = [0.0; 3];
+    for value in values.iter_mut() {
+        *value = words.next()?.parse().ok()?;
+    }
+    if words.next().is_some() {
+        return None;
+    }
+    Some(values)
+}
+
+// f32 转换为半精度浮点数的位，舍入到最近的偶数；值已经限制在 0..1，不会出现 NaN
+fn f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    // 半精度中被舍去的低位，以及舍去后的结果
+    let (half, rest, shift) = if exponent <= 0 {
+        // 非规格化数
+        if exponent < -10 {
+            return sign;
+        }
+        let shift = (14 - exponent) as u32;
+        let mantissa = mantissa | 0x80_0000;
+        (mantissa >> shift, mantissa & ((1 << shift) - 1), shift)
+    } else {
+        (
+            ((exponent as u32) << 10) | (mantissa >> 13),
+            mantissa & 0x1fff,
+            13,
+        )
+    };
+    let halfway = 1 << (shift - 1);
+    // 尾数进位到指数时结果依然正确
+    let rounded = if rest > halfway || (rest == halfway && half & 1 == 1) {
+        half + 1
+    } else {
+        half
+    };
+    sign | rounded as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube_text(header: &str, entries: &[[f32; 3]]) -> String {
+        let mut text = String::from(header);
+        for [r, g, b] in entries {
+            text.push_str(&format!("{:.6} {:.6} {:.6}\n", r, g, b));
+        }
+        text
+    }
+
+    fn assert_close(a: &Lut3D, b: &Lut3D, tolerance: f32) {
+        assert_eq!(a.size, b.size);
+        for (x, y) in a.data.iter().zip(b.data.iter()) {
+            for c in 0..3 {
+                assert!((x[c] - y[c]).abs() <= tolerance, "{:?} != {:?}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn identity_cube_round_trip() {
+        for size in [17, 33, 65] {
+            let identity = Lut3D::identity(size);
+            let header = format!("# comment\nTITLE \"identity\"\nLUT_3D_SIZE {}\n\n", size);
+            let lut = Lut3D::from_cube(&cube_text(&header, &identity.data)).unwrap();
+            assert_close(&lut, &identity, 1e-5);
+        }
+    }
+
+    #[test]
+    fn non_unit_domain_is_resampled() {
+        // 在 0..2 的 domain 上的恒等映射，重新采样到 0..1 后仍然是恒等映射
+        let entries: Vec<[f32; 3]> = Lut3D::identity(5)
+            .data
+            .iter()
+            .map(|c| c.map(|v| v * 2.0))
+            .collect();
+        let header = "LUT_3D_SIZE 5\nDOMAIN_MIN 0 0 0\nDOMAIN_MAX 2 2 2\n";
+        let lut = Lut3D::from_cube(&cube_text(header, &entries)).unwrap();
+        assert_close(&lut, &Lut3D::identity(5), 1e-5);
+
+        let header = "LUT_3D_SIZE 5\nDOMAIN_MIN 0 0 0\nDOMAIN_MAX 1 0 1\n";
+        assert!(matches!(
+            Lut3D::from_cube(&cube_text(header, &entries)),
+            Err(LutError::InvalidDomain)
+        ));
+    }
+
+    #[test]
+    fn wrong_entry_count() {
+        let entries = &Lut3D::identity(2).data[..7];
+        match Lut3D::from_cube(&cube_text("LUT_3D_SIZE 2\n", entries)) {
+            Err(LutError::EntryCount { expected, found }) => {
+                assert_eq!((expected, found), (8, 7));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn missing_or_unsupported_size() {
+        let entries = &Lut3D::identity(2).data;
+        assert!(matches!(
+            Lut3D::from_cube(&cube_text("", entries)),
+            Err(LutError::MissingSize)
+        ));
+        assert!(matches!(
+            Lut3D::from_cube(&cube_text("LUT_3D_SIZE 66\n", entries)),
+            Err(LutError::UnsupportedSize(66))
+        ));
+        match Lut3D::from_cube("LUT_3D_SIZE 2\n0 0\n") {
+            Err(LutError::Parse { line, .. }) => assert_eq!(line, 2),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn level_2_hald_image() {
+        // level 2：8x8 的图像描述 4³ 的 LUT，红色变化最快
+        let image = image::RgbaImage::from_fn(8, 8, |x, y| {
+            let i = y * 8 + x;
+            let level = |v: u32| (v * 255 / 3) as u8;
+            image::Rgba([level(i % 4), level(i / 4 % 4), level(i / 16), 255])
+        });
+        let lut = Lut3D::from_hald_image(&image).unwrap();
+        assert_close(&lut, &Lut3D::identity(4), 1.0 / 255.0);
+
+        let image = image::RgbaImage::new(8, 4);
+        assert!(matches!(
+            Lut3D::from_hald_image(&image),
+            Err(LutError::InvalidHaldSize {
+                width: 8,
+                height: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn half_float_bits() {
+        assert_eq!(f16_bits(0.0), 0x0000);
+        assert_eq!(f16_bits(1.0), 0x3c00);
+        assert_eq!(f16_bits(0.5), 0x3800);
+        assert_eq!(f16_bits(1.0 / 3.0), 0x3555);
+        // 最小的非规格化数
+        assert_eq!(f16_bits(2.0_f32.powi(-24)), 0x0001);
+    }
+}
@@ -1,226 +1,325 @@
-use super::Example;
-use app_surface::{AppSurface, SurfaceFrame};
-
 use bytemuck::{Pod, Zeroable};
+use idroid::{
+    geometry::Plane,
+    vertex::{PosTex, Vertex},
+    BufferObj,
+};
+use std::f32::consts::PI;
+use wgpu::util::DeviceExt;
+use wgpu::{BindingType, Buffer, BufferBindingType, ShaderModule, ShaderStages};
 
-use std::cell::RefCell;
-use std::cmp::PartialEq;
-use std::f32::consts::FRAC_PI_2;
-use std::rc::Rc;
+// 页面的高度为 2，宽度为 2 * aspect
+const CURL_RADIUS: f32 = 1.0 / 8.0;
+// 卷起的页面需要足够细分的网格才能弯曲得平滑
+const PLANE_SEGMENTS: u32 = 80;
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
 
+/// `TurningUniform` of `page_turning.wgsl`
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
 struct TurningUniform {
     radius: f32,
-    angle: f32,
+    aspect: f32,
     np: [f32; 2],
     n: [f32; 2],
-    alpha: f32,
-    any: f32,
 }
 
-#[derive(PartialEq)]
-enum TurningAnimationStatus {
-    ScaleIn,
-    Turning,
-    ScaleOut,
+impl TurningUniform {
+    // 把页面右下角拖到 corner 时的卷曲轴
+    fn new(aspect: f32, corner: [f32; 2]) -> Self {
+        let start = [aspect, -1.0];
+        let v = [start[0] - corner[0], start[1] - corner[1]];
+        let distance = (v[0] * v[0] + v[1] * v[1]).sqrt();
+        let n = if distance > 1e-4 {
+            [v[0] / distance, v[1] / distance]
+        } else {
+            [1.0, 0.0]
+        };
+        // 角点翻过半个圆柱后正好落在 corner 上；刚开始拖动时圆柱逐渐形成，页面不会突然卷起
+        let half_circle = PI * CURL_RADIUS * (distance / (PI * CURL_RADIUS)).min(1.0);
+        let l = (distance + half_circle) / 2.0;
+        Self {
+            radius: CURL_RADIUS,
+            aspect,
+            np: [start[0] - n[0] * l, start[1] - n[1] * l],
+            n,
+        }
+    }
 }
 
-pub struct PageTurning {
-    paper: Rc<RefCell<BackPaper>>,
-    page_rect: Rect,
-    turning_buf: BufferObj,
-    turning_uniform: TurningUniform,
-    turning_node: ViewNode,
-    depth_texture_view: wgpu::TextureView,
-    is_animating: bool,
-    roll_length: f32,
-    start_pos: Position,
-    target_pos: Position,
-    gap_pos: Position,
-    animate_index: u32,
-    pub draw_count: u32,
+/// How the bottom right corner of the page moves during a `Transition::PageCurl`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum PageCurl {
+    /// Follows the transition progress until the page is curled away
+    Auto,
+    /// Follows the touch position, in page coordinates
+    Dragging([f32; 2]),
+    /// Animates from where the touch was released, back to the corner if `cancel`
+    Released { from: [f32; 2], cancel: bool },
 }
 
-fn init_turning_uniform() -> TurningUniform {
-    TurningUniform {
-        radius: 1.0 / 8.0,
-        angle: 0.0,
-        alpha: 1.0,
-        np: [0.0, 0.0],
-        n: [0.0, 0.0],
-        any: 0.0,
+impl PageCurl {
+    /// Position of the dragged corner at `progress`, in page coordinates where the page spans
+    /// `[-aspect, aspect] x [-1, 1]`
+    pub fn corner(&self, aspect: f32, progress: f32) -> [f32; 2] {
+        let start = [aspect, -1.0];
+        // 页面完全翻过，卷起的部分也已移出屏幕的位置
+        let end = [-aspect - 4.0, 1.5];
+        let lerp = |a: [f32; 2], b: [f32; 2]| {
+            [
+                a[0] + (b[0] - a[0]) * progress,
+                a[1] + (b[1] - a[1]) * progress,
+            ]
+        };
+        match *self {
+            Self::Auto => lerp(start, end),
+            Self::Dragging(pos) => pos,
+            Self::Released { from, cancel } => lerp(from, if cancel { start } else { end }),
+        }
     }
 }
 
-impl PageTurning {
-    pub fn new(app_surface: &mut AppSurface) -> Self {
-        let device = &app_surface.device;
-        let queue = &app_surface.queue;
-        let config = &app_surface.config;
-        let scale_factor = app_surface.scale_factor;
-        let encoder =
-            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-
-        let turning_uniform = init_turning_uniform();
-        let turning_buf = BufferObj::create_uniform_buffer(device, &turning_uniform, None);
+/// Map a position in surface pixels (y down) to page coordinates (y up)
+pub(crate) fn page_position(x: f32, y: f32, size: (f32, f32)) -> [f32; 2] {
+    let aspect = size.0 / size.1;
+    [(x / size.0 * 2.0 - 1.0) * aspect, 1.0 - y / size.1 * 2.0]
+}
 
-        // Create the vertex and index buffers
-        let vpw = config.width as f32;
-        let vph = config.height as f32;
-        let (vertices, indices) = Plane::new_by_rect(
-            page_rect,
-            (vpw / (scale_factor * 2.0)) as u32,
-            (vph / (scale_factor * 2.0)) as u32,
-        )
-        .generate_vertices_by_texcoord2(Rect::from_origin_n_size(0.0, 0.0, 1.0, 1.0), None);
+/// Curls the front texture away like a page to reveal the back texture
+///
+/// Instance 0 draws the back texture flat, instance 1 draws the front texture with its vertices
+/// rolled around a cylinder; a depth buffer keeps the part flipped over on top.
+pub(crate) struct PageTurningNode {
+    turning_buf: BufferObj,
+    vertex_buf: BufferObj,
+    index_buf: wgpu::Buffer,
+    index_count: usize,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: Option<wgpu::BindGroup>,
+    depth_view: Option<wgpu::TextureView>,
+    size: (u32, u32),
+}
 
-        let bg_texture = &paper.borrow().texture;
-        let sampler = &paper.borrow().sampler;
-        let builder = ViewNodeBuilder::<PosTex2>::new(
-            vec![(bg_texture, None), (tex, None)],
-            &shaders.page_turning,
-        )
-        .with_uniform_buffers(vec![view_mvp_buf, &turning_buf])
-        .with_vertices_and_indices((vertices, indices))
-        .with_samplers(vec![sampler])
-        .with_use_depth_stencil(true)
-        .with_shader_stages(vec![
-            wgpu::ShaderStages::VERTEX,
-            wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-            wgpu::ShaderStages::FRAGMENT,
-            wgpu::ShaderStages::FRAGMENT,
-            wgpu::ShaderStages::FRAGMENT,
-        ]);
-        let turning_node = builder.build(device);
+impl PageTurningNode {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        shader_module: &ShaderModule,
+    ) -> Self {
+        let turning_uniform = TurningUniform::new(1.0, [1.0, -1.0]);
+        let turning_buf =
+            BufferObj::create_uniform_buffer(device, &turning_uniform, Some("turning uniform"));
 
-        let size = wgpu::Extent3d {
-            width: config.width,
-            height: config.height,
-            depth_or_array_layers: 1,
+        let buffer_entry = |binding, visibility| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: wgpu::BufferSize::new(0),
+            },
+            count: None,
         };
-        let depth_texture_view = crate::depth_stencil::create_depth_texture_view(size, device);
-        queue.submit(Some(encoder.finish()));
-
-        let start_pos = Position::new(1.0, 0.0);
-        //  NDC 坐标是 （-1， -1）在左下角
-        let target_pos = Position::new(-5.8, 2.5);
-
-        let maximum_frames = 60;
-        let instance = PageTurning {
-            paper: paper.clone(),
-            page_rect,
-            turning_buf,
-            turning_uniform,
-            turning_node,
-            depth_texture_view,
-            is_animating: false,
-            start_pos,
-            target_pos,
-            gap_pos: target_pos.minus(&start_pos),
-            roll_length: 0.0,
-            animate_index: 0,
-            draw_count: (maximum_frames * 1.0) as u32,
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
         };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                buffer_entry(0, ShaderStages::VERTEX),
+                buffer_entry(1, ShaderStages::VERTEX | ShaderStages::FRAGMENT),
+                texture_entry(2),
+                texture_entry(3),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("page turning bind group layout"),
+        });
 
-        instance
-    }
-
-    pub fn reset_status(&mut self) {
-        self.is_animating = false;
-        self.turning_uniform.np = Position::zero().into();
-        self.turning_uniform.n = Position::zero().into();
-        self.animate_index = 0;
-    }
-
-    fn step_frame_data(&mut self, is_out: bool) -> bool {
-        if self.animate_index > self.draw_count {
-            return true;
-        }
-        let is_stopped = false;
-        self.animate_index += 1;
-        // 由慢到快的缓动效果
-        let step = 1.0 - (FRAC_PI_2 * (self.animate_index as f32 / self.draw_count as f32)).cos();
-        let step_pos = self.gap_pos.multiply_f(step);
-        let (dx, dy) = if is_out {
-            (-step_pos.x, -step_pos.y)
-        } else {
-            (step_pos.x, step_pos.y)
-        };
+        let (vertex_data, index_data) =
+            Plane::new(PLANE_SEGMENTS, PLANE_SEGMENTS).generate_vertices();
+        let vertex_buf = BufferObj::create_buffer(
+            device,
+            Some(&vertex_data),
+            None,
+            wgpu::BufferUsages::VERTEX,
+            Some("page vertex buffer"),
+        );
+        let index_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("page index buffer"),
+            contents: bytemuck::cast_slice(&index_data),
+            usage: wgpu::BufferUsages::INDEX,
+        });
 
-        let distance = (dx * dx + dy * dy).sqrt();
-        let half_circle = std::f32::consts::PI * self.turning_uniform.radius;
-        let pi_2 = FRAC_PI_2;
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let vertex_attributes = PosTex::vertex_attributes(0);
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("page turning pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: shader_module,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<PosTex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &vertex_attributes,
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader_module,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                front_face: wgpu::FrontFace::Ccw,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
 
-        let a = -dy.atan2(dx);
-        let sin_a = a.sin();
-        let cos_a = a.cos();
-        // 最大可卷起距离
-        let mut max_roll = 0.0;
-        if a < pi_2 && a > (-pi_2) {
-            max_roll = (cos_a * (2.0 * 2.0)).abs();
+        Self {
+            turning_buf,
+            vertex_buf,
+            index_buf,
+            index_count: index_data.len(),
+            bind_group_layout,
+            pipeline,
+            bind_group: None,
+            depth_view: None,
+            size: (0, 0),
         }
+    }
 
-        // 实际的卷起距离
-        self.roll_length = distance;
-        if distance > half_circle {
-            self.roll_length = (distance - half_circle) / 2.0 + half_circle;
-        }
-        if self.roll_length > max_roll {
-            self.roll_length = max_roll;
+    /// Bind the page (`front_view`) and what it reveals (`back_view`), both of `size`
+    pub fn update_bind_group(
+        &mut self,
+        device: &wgpu::Device,
+        mvp_buffer: &Buffer,
+        sampler: &wgpu::Sampler,
+        front_view: &wgpu::TextureView,
+        back_view: &wgpu::TextureView,
+        size: (u32, u32),
+    ) {
+        self.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: mvp_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.turning_buf.buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(front_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(back_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+            label: Some("page turning bind group"),
+        }));
+        if self.depth_view.is_none() || self.size != size {
+            let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("page turning depth texture"),
+                size: wgpu::Extent3d {
+                    width: size.0.max(1),
+                    height: size.1.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: DEPTH_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            });
+            self.depth_view =
+                Some(depth_texture.create_view(&wgpu::TextureViewDescriptor::default()));
+            self.size = size;
         }
-        self.turning_uniform.angle = a;
-        self.turning_uniform.np = [
-            self.page_rect.center_x() - (cos_a * self.roll_length).abs(),
-            self.page_rect.center_y() * (if a > 0.0 { 1.0 } else { -1.0 })
-                - sin_a * self.roll_length,
-        ];
-        self.turning_uniform.n = [cos_a, sin_a];
-
-        is_stopped
     }
-}
 
-impl Example for PageTurning {
-    fn enter_frame(&mut self, app_surface: &AppSurface) {
-        let device = &app_surface.device;
-        let queue = &app_surface.queue;
-        // instance 0 只绘制 paper, instance 1 计算 turning
-        let instance_count: u32 = 2;
-        let is_turing_completed = self.step_frame_data(true);
-        if is_turing_completed {
-            self.is_animating = false;
-        } else {
-            self.is_animating = true;
-        }
+    /// Draw the page with its bottom right corner dragged to `corner`, see `PageCurl::corner`
+    pub fn draw(
+        &self,
+        queue: &wgpu::Queue,
+        frame_view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+        corner: [f32; 2],
+    ) {
+        let (bind_group, depth_view) = match (&self.bind_group, &self.depth_view) {
+            (Some(bind_group), Some(depth_view)) => (bind_group, depth_view),
+            _ => return,
+        };
+        let aspect = self.size.0 as f32 / self.size.1.max(1) as f32;
+        let turning_uniform = TurningUniform::new(aspect, corner);
         queue.write_buffer(
             &self.turning_buf.buffer,
             0,
-            bytemuck::cast_slice(&self.turning_uniform),
+            bytemuck::bytes_of(&turning_uniform),
         );
-        let (frame, view) = app_surface.get_current_frame_view();
-        let mut encoder =
-            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
         let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: None,
-            color_attachments: &[wgpu::RenderPassColorAttachment {
-                view: &view,
+            label: Some("page turning pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: frame_view,
                 resolve_target: None,
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(self.paper.borrow().clear_color),
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
                     store: true,
                 },
-            }],
+            })],
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &self.depth_texture_view,
+                view: depth_view,
                 depth_ops: Some(wgpu::Operations {
                     load: wgpu::LoadOp::Clear(1.0),
-                    store: true,
+                    store: false,
                 }),
                 stencil_ops: None,
             }),
         });
-        self.turning_node
-            .draw_by_instance_count(&mut rpass, instance_count);
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, bind_group, &[]);
+        rpass.set_index_buffer(self.index_buf.slice(..), wgpu::IndexFormat::Uint32);
+        rpass.set_vertex_buffer(0, self.vertex_buf.buffer.slice(..));
+        rpass.set_viewport(0.0, 0.0, self.size.0 as f32, self.size.1 as f32, 0.0, 1.0);
+        // instance 0 只绘制被露出的画面, instance 1 绘制卷起的页面
+        rpass.draw_indexed(0..self.index_count as u32, 0, 0..2);
     }
 }
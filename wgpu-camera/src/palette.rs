@@ -0,0 +1,130 @@
+use crate::filter_params::MAX_PALETTE_COLORS;
+use serde::{Deserialize, Serialize};
+
+/// Why a palette swatch could not be loaded
+#[derive(Debug)]
+pub enum PaletteError {
+    Png(png::DecodingError),
+    /// A swatch PNG must be RGB or RGBA
+    UnsupportedColorType(png::ColorType),
+    /// The swatch has no opaque pixels
+    Empty,
+    /// The swatch has more distinct colors than `MAX_PALETTE_COLORS`
+    TooManyColors(usize),
+}
+
+impl std::fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Png(e) => write!(f, "invalid palette png: {}", e),
+            Self::UnsupportedColorType(ty) => write!(f, "unsupported palette color type {:?}", ty),
+            Self::Empty => f.write_str("the palette has no colors"),
+            Self::TooManyColors(count) => write!(
+                f,
+                "the palette has {} colors, at most {} are supported",
+                count, MAX_PALETTE_COLORS
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PaletteError {}
+
+impl From<png::DecodingError> for PaletteError {
+    fn from(e: png::DecodingError) -> Self {
+        Self::Png(e)
+    }
+}
+
+/// Colors `FilterType::Dither` quantizes to, an empty palette quantizes each channel to
+/// `DitherParams::levels` levels instead
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Palette {
+    colors: Vec<[u8; 3]>,
+}
+
+impl Palette {
+    /// At most `MAX_PALETTE_COLORS` colors
+    pub fn new(colors: Vec<[u8; 3]>) -> Result<Self, PaletteError> {
+        if colors.len() > MAX_PALETTE_COLORS {
+            return Err(PaletteError::TooManyColors(colors.len()));
+        }
+        Ok(Self { colors })
+    }
+
+    /// The four greens of the original Game Boy
+    pub fn game_boy() -> Self {
+        Self::from_hex(&[0x0f380f, 0x306230, 0x8bac0f, 0x9bbc0f])
+    }
+
+    /// CGA palette 1 in high intensity: black, cyan, magenta and white
+    pub fn cga() -> Self {
+        Self::from_hex(&[0x000000, 0x55ffff, 0xff55ff, 0xffffff])
+    }
+
+    /// The 16 colors of the PICO-8 fantasy console
+    pub fn pico8() -> Self {
+        Self::from_hex(&[
+            0x000000, 0x1d2b53, 0x7e2553, 0x008751, 0xab5236, 0x5f574f, 0xc2c3c7, 0xfff1e8,
+            0xff004d, 0xffa300, 0xffec27, 0x00e436, 0x29adff, 0x83769c, 0xff77a8, 0xffccaa,
+        ])
+    }
+
+    /// The distinct opaque colors of a swatch PNG, in reading order
+    pub fn from_png_swatch(bytes: &[u8]) -> Result<Self, PaletteError> {
+        let mut decoder = png::Decoder::new(std::io::Cursor::new(bytes));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        let pixels: Vec<[u8; 4]> = match info.color_type {
+            png::ColorType::Rgb => buf[..info.buffer_size()]
+                .chunks_exact(3)
+                .map(|p| [p[0], p[1], p[2], 255])
+                .collect(),
+            png::ColorType::Rgba => buf[..info.buffer_size()]
+                .chunks_exact(4)
+                .map(|p| [p[0], p[1], p[2], p[3]])
+                .collect(),
+            ty => return Err(PaletteError::UnsupportedColorType(ty)),
+        };
+        Self::from_pixels(pixels.into_iter())
+    }
+
+    /// The distinct opaque colors of a swatch that has already been decoded, e.g. by the
+    /// `image` crate
+    pub fn from_image(image: &image::RgbaImage) -> Result<Self, PaletteError> {
+        Self::from_pixels(image.pixels().map(|p| p.0))
+    }
+
+    pub fn colors(&self) -> &[[u8; 3]] {
+        &self.colors
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    fn from_hex(colors: &[u32]) -> Self {
+        let colors = colors
+            .iter()
+            .map(|c| [(c >> 16) as u8, (c >> 8) as u8, *c as u8])
+            .collect();
+        Self { colors }
+    }
+
+    fn from_pixels(pixels: impl Iterator<Item = [u8; 4]>) -> Result<Self, PaletteError> {
+        let mut colors: Vec<[u8; 3]> = vec![];
+        // 半透明的像素通常是色块之间的间隔或抗锯齿边缘
+        for [r, g, b, a] in pixels {
+            if a == 255 && !colors.contains(&[r, g, b]) {
+                colors.push([r, g, b]);
+            }
+        }
+        if colors.is_empty() {
+            return Err(PaletteError::Empty);
+        }
+        Self::new(colors)
+    }
+}
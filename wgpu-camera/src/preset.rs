@@ -0,0 +1,259 @@
+use crate::filter_params::{
+    AsciiArtParams, ColorAdjustParams, CrossHatchParams, DitherParams, EdgeDetectionParams,
+    FilterParams, GaussianBlurParams, HalftoneParams, Lut3DParams, ToneCurveParams,
+};
+use crate::layout::{ContentMode, CropRect, Mirror, ViewTransform};
+use crate::FilterType;
+use serde::{Deserialize, Serialize};
+
+/// Version written by `Preset::new`, presets with a newer version are rejected
+pub const PRESET_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum PresetError {
+    Json(serde_json::Error),
+    TomlDe(toml::de::Error),
+    TomlSer(toml::ser::Error),
+    UnsupportedVersion(u32),
+    /// The preset uses a custom filter that has not been registered with `register_filter`
+    UnknownCustomFilter(String),
+}
+
+impl std::fmt::Display for PresetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Json(e) => write!(f, "invalid preset json: {}", e),
+            Self::TomlDe(e) => write!(f, "invalid preset toml: {}", e),
+            Self::TomlSer(e) => write!(f, "can't write preset toml: {}", e),
+            Self::UnsupportedVersion(v) => write!(
+                f,
+                "unsupported preset version {}, the newest supported is {}",
+                v, PRESET_VERSION
+            ),
+            Self::UnknownCustomFilter(name) => write!(f, "unknown custom filter \"{}\"", name),
+        }
+    }
+}
+
+impl std::error::Error for PresetError {}
+
+/// One stage of the filter chain, `{ "filter": "AsciiArt", "cell_size": 8.0 }`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "filter")]
+pub enum StagePreset {
+    Original,
+    AsciiArt(AsciiArtParams),
+    CrossHatch(CrossHatchParams),
+    EdgeDetection(EdgeDetectionParams),
+    GaussianBlur(GaussianBlurParams),
+    Custom(CustomStagePreset),
+    /// The LUT itself is not part of the preset, the stage starts with an identity LUT
+    Lut3D(Lut3DParams),
+    ColorAdjust(ColorAdjustParams),
+    ToneCurve(ToneCurveParams),
+    Halftone(HalftoneParams),
+    Dither(DitherParams),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomStagePreset {
+    /// Name the filter was registered with
+    pub name: String,
+    pub params: Vec<f32>,
+}
+
+impl StagePreset {
+    pub(crate) fn new(params: &FilterParams, custom_name: Option<&str>) -> Self {
+        match params {
+            FilterParams::Original => Self::Original,
+            FilterParams::AsciiArt(p) => Self::AsciiArt(*p),
+            FilterParams::CrossHatch(p) => Self::CrossHatch(*p),
+            FilterParams::EdgeDetection(p) => Self::EdgeDetection(*p),
+            FilterParams::GaussianBlur(p) => Self::GaussianBlur(*p),
+            FilterParams::Lut3D(p) => Self::Lut3D(*p),
+            FilterParams::ColorAdjust(p) => Self::ColorAdjust(*p),
+            FilterParams::ToneCurve(p) => Self::ToneCurve(p.clone()),
+            FilterParams::Halftone(p) => Self::Halftone(*p),
+            FilterParams::Dither(p) => Self::Dither(p.clone()),
+            FilterParams::Custom(data) => Self::Custom(CustomStagePreset {
+                name: custom_name.unwrap_or_default().to_string(),
+                params: data.clone(),
+            }),
+        }
+    }
+
+    pub fn filter_type(&self) -> FilterType {
+        self.params().filter_type()
+    }
+
+    pub fn custom_name(&self) -> Option<&str> {
+        match self {
+            Self::Custom(custom) => Some(&custom.name),
+            _ => None,
+        }
+    }
+
+    pub fn params(&self) -> FilterParams {
+        match self {
+            Self::Original => FilterParams::Original,
+            Self::AsciiArt(p) => FilterParams::AsciiArt(*p),
+            Self::CrossHatch(p) => FilterParams::CrossHatch(*p),
+            Self::EdgeDetection(p) => FilterParams::EdgeDetection(*p),
+            Self::GaussianBlur(p) => FilterParams::GaussianBlur(*p),
+            Self::Lut3D(p) => FilterParams::Lut3D(*p),
+            Self::ColorAdjust(p) => FilterParams::ColorAdjust(*p),
+            Self::ToneCurve(p) => FilterParams::ToneCurve(p.clone()),
+            Self::Halftone(p) => FilterParams::Halftone(*p),
+            Self::Dither(p) => FilterParams::Dither(p.clone()),
+            Self::Custom(custom) => FilterParams::Custom(custom.params.clone()),
+        }
+    }
+}
+
+/// Orientation and transform settings of a preset, applied all together
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutPreset {
+    pub content_mode: ContentMode,
+    /// Device rotation in degrees
+    pub device_rotation: f32,
+    /// `None` shows the whole source texture
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_crop: Option<CropRect>,
+    pub view_transform: ViewTransform,
+    pub preview_mirror: Mirror,
+    pub export_mirror: Mirror,
+}
+
+/// A named look: the filter chain with all of its parameters and the canvas settings
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Preset {
+    pub version: u32,
+    pub name: String,
+    pub opaque_background_color: bool,
+    /// Camera sensor orientation in degrees, not applied when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sensor_orientation: Option<f32>,
+    /// Content mode, crop, view transform, mirroring and device rotation; presets written
+    /// before these settings existed leave them unchanged
+    // toml 要求普通的值写在表之前，所以放在 stages 前面
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<LayoutPreset>,
+    /// In the order they are applied
+    pub stages: Vec<StagePreset>,
+}
+
+impl Preset {
+    pub fn new(name: &str) -> Self {
+        Self {
+            version: PRESET_VERSION,
+            name: name.to_string(),
+            opaque_background_color: false,
+            sensor_orientation: None,
+            layout: None,
+            stages: vec![],
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, PresetError> {
+        let preset: Self = serde_json::from_str(json).map_err(PresetError::Json)?;
+        preset.check_version()
+    }
+
+    pub fn to_json(&self) -> Result<String, PresetError> {
+        serde_json::to_string_pretty(self).map_err(PresetError::Json)
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Self, PresetError> {
+        let preset: Self = toml::from_str(toml).map_err(PresetError::TomlDe)?;
+        preset.check_version()
+    }
+
+    pub fn to_toml(&self) -> Result<String, PresetError> {
+        toml::to_string(self).map_err(PresetError::TomlSer)
+    }
+
+    fn check_version(self) -> Result<Self, PresetError> {
+        if self.version == 0 || self.version > PRESET_VERSION {
+            return Err(PresetError::UnsupportedVersion(self.version));
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_layout_loads_with_defaults() {
+        let json = r#"{
+            "version": 1,
+            "name": "zoom",
+            "opaque_background_color": false,
+            "layout": { "view_transform": { "scale": 2.0 }, "preview_mirror": { "horizontal": true } },
+            "stages": [{ "filter": "Original" }]
+        }"#;
+        let layout = Preset::from_json(json).unwrap().layout.unwrap();
+        assert_eq!(layout.view_transform.scale, 2.0);
+        assert_eq!(layout.view_transform.rotation, 0.0);
+        assert_eq!(layout.preview_mirror, Mirror::new(true, false));
+        assert_eq!(layout.export_mirror, Mirror::NONE);
+    }
+
+    #[test]
+    fn unknown_nested_keys_are_rejected() {
+        let preset = |layout: &str| {
+            format!(
+                r#"{{ "version": 1, "name": "x", "opaque_background_color": false,
+                    "layout": {}, "stages": [] }}"#,
+                layout
+            )
+        };
+        for layout in [
+            r#"{ "view_transform": { "scale": 2.0, "rotaton": 90.0 } }"#,
+            r#"{ "export_mirror": { "horizontl": true } }"#,
+            r#"{ "source_crop": { "x": 0.0, "y": 0.0, "width": 1.0, "height": 1.0, "z": 0.0 } }"#,
+            r#"{ "content_mod": "AspectFit" }"#,
+        ] {
+            assert!(
+                matches!(
+                    Preset::from_json(&preset(layout)),
+                    Err(PresetError::Json(_))
+                ),
+                "{} was accepted",
+                layout
+            );
+        }
+    }
+
+    #[test]
+    fn layout_round_trip() {
+        let mut preset = Preset::new("layout");
+        preset.layout = Some(LayoutPreset {
+            content_mode: ContentMode::AspectFill,
+            device_rotation: 90.0,
+            source_crop: Some(CropRect {
+                x: 0.25,
+                y: 0.0,
+                width: 0.5,
+                height: 1.0,
+            }),
+            view_transform: ViewTransform {
+                scale: 1.5,
+                translate: (10.0, -4.0),
+                rotation: 30.0,
+            },
+            preview_mirror: Mirror::new(true, false),
+            export_mirror: Mirror::new(false, true),
+        });
+        preset.stages.push(StagePreset::Original);
+
+        let json = preset.to_json().unwrap();
+        assert_eq!(Preset::from_json(&json).unwrap(), preset);
+        let toml = preset.to_toml().unwrap();
+        assert_eq!(Preset::from_toml(&toml).unwrap(), preset);
+    }
+}
@@ -0,0 +1,33 @@
+use crate::wgsl_preprocessor::WgslPreprocessor;
+
+// Rust 代码与内置 shader 共用的常量，build.rs 与热重载预处理 shader 时以 #define 注入，
+// 两边因此不会不一致
+
+/// Rows of the `diffusion_errors` ring buffer of `dither_diffusion.wgsl`, the error of a row
+/// reaches at most the next two rows
+pub(crate) const DITHER_ERROR_ROWS: u32 = 258;
+
+/// Define the shared constants on a preprocessor of the built-in shaders
+// 库中只有热重载会用到，build.rs 总是会用到
+#[cfg_attr(
+    not(all(
+        feature = "hot-reload",
+        not(any(target_os = "ios", target_os = "android", target_arch = "wasm32"))
+    )),
+    allow(dead_code)
+)]
+pub(crate) fn define_all(preprocessor: &mut WgslPreprocessor) {
+    preprocessor.define("DITHER_ERROR_ROWS", &format!("{}u", DITHER_ERROR_ROWS));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preprocessed_dither_diffusion_is_up_to_date() {
+        let code = include_str!("../../wgsl_preprocessed/dither_diffusion.wgsl");
+        let expected = format!("let ERROR_ROWS: u32 = {}u;", DITHER_ERROR_ROWS);
+        assert!(code.lines().any(|line| line.trim() == expected));
+    }
+}
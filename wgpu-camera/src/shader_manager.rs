@@ -1,10 +1,125 @@
-use crate::FilterType;
+use crate::wgsl_preprocessor::{
+    includes_module, PreprocessError, PreprocessedShader, SourceLocation, WgslPreprocessor,
+};
+#[cfg(all(
+    feature = "hot-reload",
+    not(any(target_os = "ios", target_os = "android", target_arch = "wasm32"))
+))]
+use crate::{compute_filter_node::ComputeFilterNode, transition_node::TransitionNode};
+use crate::{display_node::DisplayNode, FilterType};
+use idroid::vertex::PosTex;
+use std::collections::HashMap;
 use wgpu::ShaderModule;
+
+/// What uses a built-in shader, i.e. what has to be rebuilt after the shader is reloaded
+#[cfg(all(
+    feature = "hot-reload",
+    not(any(target_os = "ios", target_os = "android", target_arch = "wasm32"))
+))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderUse {
+    Filter(FilterType),
+    /// The `TransitionNode` of transitions and comparisons
+    Transition,
+}
+
+// build.rs 预处理的内置 shader 及使用它的滤镜
+#[cfg(all(
+    feature = "hot-reload",
+    not(any(target_os = "ios", target_os = "android", target_arch = "wasm32"))
+))]
+const BUILTIN_SHADERS: [(&str, ShaderUse); 14] = [
+    ("original", ShaderUse::Filter(FilterType::Original)),
+    ("ascii_art", ShaderUse::Filter(FilterType::AsciiArt)),
+    ("cross_hatching", ShaderUse::Filter(FilterType::CrossHatch)),
+    (
+        "edge_detection",
+        ShaderUse::Filter(FilterType::EdgeDetection),
+    ),
+    (
+        "gaussian_blur_x",
+        ShaderUse::Filter(FilterType::GaussianBlur),
+    ),
+    (
+        "gaussian_blur_y",
+        ShaderUse::Filter(FilterType::GaussianBlur),
+    ),
+    ("lut3d", ShaderUse::Filter(FilterType::Lut3D)),
+    ("color_adjust", ShaderUse::Filter(FilterType::ColorAdjust)),
+    ("tone_curve", ShaderUse::Filter(FilterType::ToneCurve)),
+    ("halftone", ShaderUse::Filter(FilterType::Halftone)),
+    ("dither", ShaderUse::Filter(FilterType::Dither)),
+    ("dither_diffusion", ShaderUse::Filter(FilterType::Dither)),
+    ("transition", ShaderUse::Transition),
+    ("page_turning", ShaderUse::Transition),
+];
+
+const CUSTOM_INPUT_PARAMS: &str =
+    "struct InputParams { param: f32, opaque_background_color: f32, };";
+
+/// Why a custom filter shader was rejected
+#[derive(Debug, Clone)]
+pub enum ShaderError {
+    Preprocess(PreprocessError),
+    Parse {
+        message: String,
+        location: Option<SourceLocation>,
+    },
+    Validation {
+        message: String,
+        location: Option<SourceLocation>,
+    },
+    /// The filter name contains a path separator, a quote or a comma, which would let it replace
+    /// or break the includes of the shader
+    InvalidName(String),
+    /// The shader does not define the `vs_main`/`fs_main` entry point
+    MissingEntryPoint(String),
+    /// wgpu rejected the pipeline of the shader, e.g. it declares bindings that the filter's
+    /// bind group layout does not have, or `fs_main` does not return `@location(0) vec4<f32>`
+    Pipeline(String),
+}
+
+impl std::fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Preprocess(e) => e.fmt(f),
+            Self::Parse { message, location } | Self::Validation { message, location } => {
+                match location {
+                    Some(location) => write!(f, "{}: {}", location, message),
+                    None => f.write_str(message),
+                }
+            }
+            Self::InvalidName(name) => write!(f, "invalid filter name \"{}\"", name),
+            Self::MissingEntryPoint(name) => write!(f, "missing entry point `{}`", name),
+            Self::Pipeline(message) => write!(f, "incompatible with the pipeline: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+impl From<PreprocessError> for ShaderError {
+    fn from(e: PreprocessError) -> Self {
+        Self::Preprocess(e)
+    }
+}
+
 pub struct ShaderManager {
     pub original: ShaderModule,
     pub ascii_art: ShaderModule,
     pub cross_hatch: ShaderModule,
     pub edge_detection: ShaderModule,
+    pub gaussian_blur_x: ShaderModule,
+    pub gaussian_blur_y: ShaderModule,
+    pub lut3d: ShaderModule,
+    pub color_adjust: ShaderModule,
+    pub tone_curve: ShaderModule,
+    pub halftone: ShaderModule,
+    pub dither: ShaderModule,
+    pub dither_diffusion: ShaderModule,
+    pub transition: ShaderModule,
+    pub page_turning: ShaderModule,
+    custom: HashMap<String, ShaderModule>,
 }
 
 impl ShaderManager {
@@ -30,17 +145,340 @@ impl ShaderManager {
                 include_str!("../../wgsl_preprocessed/edge_detection.wgsl"),
                 Some("edge_detection shader"),
             ),
+            gaussian_blur_x: create_shader_module(
+                device,
+                include_str!("../../wgsl_preprocessed/gaussian_blur_x.wgsl"),
+                Some("gaussian_blur_x shader"),
+            ),
+            gaussian_blur_y: create_shader_module(
+                device,
+                include_str!("../../wgsl_preprocessed/gaussian_blur_y.wgsl"),
+                Some("gaussian_blur_y shader"),
+            ),
+            lut3d: create_shader_module(
+                device,
+                include_str!("../../wgsl_preprocessed/lut3d.wgsl"),
+                Some("lut3d shader"),
+            ),
+            color_adjust: create_shader_module(
+                device,
+                include_str!("../../wgsl_preprocessed/color_adjust.wgsl"),
+                Some("color_adjust shader"),
+            ),
+            tone_curve: create_shader_module(
+                device,
+                include_str!("../../wgsl_preprocessed/tone_curve.wgsl"),
+                Some("tone_curve shader"),
+            ),
+            halftone: create_shader_module(
+                device,
+                include_str!("../../wgsl_preprocessed/halftone.wgsl"),
+                Some("halftone shader"),
+            ),
+            dither: create_shader_module(
+                device,
+                include_str!("../../wgsl_preprocessed/dither.wgsl"),
+                Some("dither shader"),
+            ),
+            dither_diffusion: create_shader_module(
+                device,
+                include_str!("../../wgsl_preprocessed/dither_diffusion.wgsl"),
+                Some("dither_diffusion shader"),
+            ),
+            transition: create_shader_module(
+                device,
+                include_str!("../../wgsl_preprocessed/transition.wgsl"),
+                Some("transition shader"),
+            ),
+            page_turning: create_shader_module(
+                device,
+                include_str!("../../wgsl_preprocessed/page_turning.wgsl"),
+                Some("page_turning shader"),
+            ),
+            custom: HashMap::new(),
+        }
+    }
+
+    /// Validate and register a fragment filter, see `validate_filter_shader`
+    ///
+    /// The shader must also fit the pipeline of a fragment filter stage that renders to `format`:
+    /// bindings 0 ~ 3 of group 0 and a `vec4<f32>` color output.
+    pub fn register_custom(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        name: &str,
+        source: &str,
+    ) -> Result<(), ShaderError> {
+        let code = validate_filter_shader(name, source)?;
+        let label = format!("{} shader", name);
+        let module = catch_validation_error(device, || {
+            device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(&label),
+                source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Owned(code)),
+            })
+        })?;
+        DisplayNode::check_shader::<PosTex>(device, format, &module, None)?;
+        self.custom.insert(name.to_string(), module);
+        Ok(())
+    }
+
+    /// Re-run the preprocessor on the built-in shaders under `wgsl_dir` that use one of
+    /// `changed_files` and rebuild their modules, returning what uses the rebuilt shaders
+    ///
+    /// A shader that fails naga validation, or that wgpu rejects when building the pipeline it
+    /// is used in, is logged and keeps its last good module.
+    #[cfg(all(
+        feature = "hot-reload",
+        not(any(target_os = "ios", target_os = "android", target_arch = "wasm32"))
+    ))]
+    pub fn reload_builtin(
+        &mut self,
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        wgsl_dir: &std::path::Path,
+        changed_files: &[String],
+    ) -> Vec<ShaderUse> {
+        let mut preprocessor = WgslPreprocessor::new().with_base_dir(wgsl_dir);
+        crate::shader_defines::define_all(&mut preprocessor);
+        let mut changed = vec![];
+        for (name, usage) in BUILTIN_SHADERS {
+            let file_name = format!("{}.wgsl", name);
+            let shader = match preprocessor.process(&file_name) {
+                Ok(shader) => shader,
+                Err(e) => {
+                    log::error!("{}", e);
+                    continue;
+                }
+            };
+            if !shader.files().iter().any(|f| changed_files.contains(f)) {
+                continue;
+            }
+            let label = format!("{} shader", name);
+            let module = validate_wgsl(&shader).and_then(|_| {
+                catch_validation_error(device, || {
+                    create_shader_module(device, &shader.code, Some(&label))
+                })
+            });
+            let module = match module.and_then(|module| {
+                self.check_builtin(device, config, name, usage, &module)?;
+                Ok(module)
+            }) {
+                Ok(module) => module,
+                Err(e) => {
+                    log::error!("{}: {}", file_name, e);
+                    continue;
+                }
+            };
+            *self.builtin_mut(name) = module;
+            if !changed.contains(&usage) {
+                changed.push(usage);
+            }
+        }
+        changed
+    }
+
+    // 用新的 module 构建一次它所在的管线
+    #[cfg(all(
+        feature = "hot-reload",
+        not(any(target_os = "ios", target_os = "android", target_arch = "wasm32"))
+    ))]
+    fn check_builtin(
+        &self,
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        name: &str,
+        usage: ShaderUse,
+        module: &ShaderModule,
+    ) -> Result<(), ShaderError> {
+        match usage {
+            ShaderUse::Filter(ty) if ty.is_compute() => {
+                ComputeFilterNode::check_pass(device, module, ty.compute_scratch_rows())
+            }
+            ShaderUse::Filter(ty) => DisplayNode::check_shader::<PosTex>(
+                device,
+                config.format,
+                module,
+                ty.aux_texture_dimension(),
+            ),
+            ShaderUse::Transition => {
+                let (transition, page_turning) = if name == "transition" {
+                    (module, &self.page_turning)
+                } else {
+                    (&self.transition, module)
+                };
+                catch_validation_error(device, || {
+                    TransitionNode::new(device, config, transition, page_turning);
+                })
+            }
         }
     }
 
+    #[cfg(all(
+        feature = "hot-reload",
+        not(any(target_os = "ios", target_os = "android", target_arch = "wasm32"))
+    ))]
+    fn builtin_mut(&mut self, name: &str) -> &mut ShaderModule {
+        match name {
+            "original" => &mut self.original,
+            "ascii_art" => &mut self.ascii_art,
+            "cross_hatching" => &mut self.cross_hatch,
+            "edge_detection" => &mut self.edge_detection,
+            "gaussian_blur_x" => &mut self.gaussian_blur_x,
+            "gaussian_blur_y" => &mut self.gaussian_blur_y,
+            "lut3d" => &mut self.lut3d,
+            "color_adjust" => &mut self.color_adjust,
+            "tone_curve" => &mut self.tone_curve,
+            "halftone" => &mut self.halftone,
+            "dither" => &mut self.dither,
+            "dither_diffusion" => &mut self.dither_diffusion,
+            "transition" => &mut self.transition,
+            "page_turning" => &mut self.page_turning,
+            _ => unreachable!("{} is not in BUILTIN_SHADERS", name),
+        }
+    }
+
+    /// Names of the registered custom filters, sorted
+    pub fn custom_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.custom.keys().map(|name| name.as_str()).collect();
+        names.sort_unstable();
+        names
+    }
+
+    pub fn get_custom_shader(&self, name: &str) -> Option<&ShaderModule> {
+        self.custom.get(name)
+    }
+
     pub fn get_shader_ref(&self, ty: FilterType) -> &ShaderModule {
         match ty {
             FilterType::Original => &self.original,
             FilterType::AsciiArt => &self.ascii_art,
             FilterType::CrossHatch => &self.cross_hatch,
             FilterType::EdgeDetection => &self.edge_detection,
+            FilterType::Lut3D => &self.lut3d,
+            FilterType::ColorAdjust => &self.color_adjust,
+            FilterType::ToneCurve => &self.tone_curve,
+            FilterType::Halftone => &self.halftone,
+            // compute 滤镜的结果直接显示
+            FilterType::GaussianBlur | FilterType::Dither => &self.original,
+            // 自定义滤镜需要通过名字查找，见 get_custom_shader
+            FilterType::Custom => &self.original,
+        }
+    }
+
+    /// Compute passes of a compute-based filter, in dispatch order
+    pub fn get_compute_shaders(&self, ty: FilterType) -> Vec<&ShaderModule> {
+        match ty {
+            FilterType::Original
+            | FilterType::AsciiArt
+            | FilterType::CrossHatch
+            | FilterType::EdgeDetection
+            | FilterType::Custom
+            | FilterType::Lut3D
+            | FilterType::ColorAdjust
+            | FilterType::ToneCurve
+            | FilterType::Halftone => vec![],
+            FilterType::GaussianBlur => vec![&self.gaussian_blur_x, &self.gaussian_blur_y],
+            FilterType::Dither => vec![&self.dither, &self.dither_diffusion],
+        }
+    }
+}
+
+/// Preprocess `source` and validate it with naga, returning the final WGSL code
+///
+/// A source that does not include `common/group0+vs.wgsl` itself gets it prepended, together with
+/// a default `InputParams { param, opaque_background_color }`. Errors are reported against `name`
+/// and the line of `source` they come from. `name` must not contain `/`, `\`, `"` or `,`.
+pub fn validate_filter_shader(name: &str, source: &str) -> Result<String, ShaderError> {
+    // 内置模块的名字都带有路径，过滤掉这些字符后自定义滤镜不会覆盖它们，也不会破坏 include 指令
+    if name.is_empty() || name.contains(['/', '\\', '"', ',']) {
+        return Err(ShaderError::InvalidName(name.to_string()));
+    }
+    let mut preprocessor = WgslPreprocessor::new();
+    preprocessor.add_source(
+        "common/group0+vs.wgsl",
+        include_str!("../../wgsl/common/group0+vs.wgsl"),
+    );
+    preprocessor.add_source(
+        "func/edge_detection.wgsl",
+        include_str!("../../wgsl/func/edge_detection.wgsl"),
+    );
+    preprocessor.add_source(name, source);
+    // 与内置滤镜一样自己定义了 InputParams 并引入 group0 的，直接使用；
+    // 否则使用默认的 InputParams，与 WgpuCanvas 写入自定义滤镜的参数一致
+    let shader = if includes_module(source, "common/group0+vs.wgsl") {
+        preprocessor.process(name)?
+    } else {
+        let root = format!(
+            "{}\n#include \"common/group0+vs.wgsl\", \"{}\"",
+            CUSTOM_INPUT_PARAMS, name
+        );
+        // 带路径的根模块名不会与自定义滤镜重名
+        preprocessor.process_source("custom/filter", &root)?
+    };
+
+    let module = validate_wgsl(&shader)?;
+    for (entry_point, stage) in [
+        ("vs_main", naga::ShaderStage::Vertex),
+        ("fs_main", naga::ShaderStage::Fragment),
+    ] {
+        if !module
+            .entry_points
+            .iter()
+            .any(|ep| ep.name == entry_point && ep.stage == stage)
+        {
+            return Err(ShaderError::MissingEntryPoint(entry_point.to_string()));
         }
     }
+    Ok(shader.code)
+}
+
+/// Parse and validate preprocessed WGSL with naga, so that invalid code never reaches wgpu
+pub fn validate_wgsl(shader: &PreprocessedShader) -> Result<naga::Module, ShaderError> {
+    let module = naga::front::wgsl::parse_str(&shader.code).map_err(|e| ShaderError::Parse {
+        message: e.message().to_string(),
+        location: e
+            .location(&shader.code)
+            .and_then(|l| shader.location_of_offset(l.offset as usize)),
+    })?;
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&module)
+    .map_err(|e| ShaderError::Validation {
+        location: e
+            .location(&shader.code)
+            .and_then(|l| shader.location_of_offset(l.offset as usize)),
+        message: e.into_inner().to_string(),
+    })?;
+    Ok(module)
+}
+
+/// Run `f` inside a validation error scope, so that a wgpu validation error is returned instead of
+/// reaching the uncaptured error handler, which panics
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn catch_validation_error<T>(
+    device: &wgpu::Device,
+    f: impl FnOnce() -> T,
+) -> Result<T, ShaderError> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let value = f();
+    // 原生后端的 error scope 是同步的，future 立即完成
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(e) => Err(ShaderError::Pipeline(e.to_string())),
+        None => Ok(value),
+    }
+}
+
+/// On wasm32 the error scope of the WebGPU backend resolves asynchronously and blocking on it
+/// would hang the only thread, so only the naga validation that already ran applies
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn catch_validation_error<T>(
+    _device: &wgpu::Device,
+    f: impl FnOnce() -> T,
+) -> Result<T, ShaderError> {
+    Ok(f())
 }
 
 fn create_shader_module(device: &wgpu::Device, shader: &str, label: Option<&str>) -> ShaderModule {
@@ -49,3 +487,54 @@ fn create_shader_module(device: &wgpu::Device, shader: &str, label: Option<&str>
         source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(shader)),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FS: &str = "@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(tex, tex_sampler, vertex.uv) * params[0].param;
+}
+";
+
+    #[test]
+    fn names_with_path_or_quote_characters_are_rejected() {
+        for name in ["common/group0+vs.wgsl", "a\\b", "a\"b", "a,b", ""] {
+            assert!(matches!(
+                validate_filter_shader(name, FS),
+                Err(ShaderError::InvalidName(_))
+            ));
+        }
+        assert!(validate_filter_shader("filter", FS).is_ok());
+    }
+
+    #[test]
+    fn only_an_include_directive_skips_the_default_params() {
+        // 注释中提到 group0 不算引入，仍然使用默认的 InputParams
+        let commented = format!("// based on common/group0+vs.wgsl\n{}", FS);
+        assert!(validate_filter_shader("commented", &commented).is_ok());
+
+        let own_params = format!(
+            "struct InputParams {{ param: f32, }};\n///#include \"common/group0+vs.wgsl\"\n{}",
+            FS
+        );
+        assert!(validate_filter_shader("own_params", &own_params).is_ok());
+    }
+
+    #[test]
+    fn errors_point_at_the_custom_source() {
+        let source = format!("{}\nfn broken() -> f32 {{ return missing; }}\n", FS);
+        match validate_filter_shader("broken", &source) {
+            Err(ShaderError::Parse {
+                location: Some(location),
+                ..
+            })
+            | Err(ShaderError::Validation {
+                location: Some(location),
+                ..
+            }) => assert_eq!(location.file, "broken"),
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+/// Watches the `wgsl/` directory so that shaders can be rebuilt while the app is running
+pub(crate) struct ShaderWatcher {
+    wgsl_dir: PathBuf,
+    _watcher: RecommendedWatcher,
+    receiver: mpsc::Receiver<notify::Result<notify::Event>>,
+}
+
+impl ShaderWatcher {
+    pub fn new(wgsl_dir: &Path) -> notify::Result<Self> {
+        let wgsl_dir = wgsl_dir.canonicalize().map_err(notify::Error::io)?;
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        })?;
+        watcher.watch(&wgsl_dir, RecursiveMode::Recursive)?;
+        Ok(Self {
+            wgsl_dir,
+            _watcher: watcher,
+            receiver,
+        })
+    }
+
+    pub fn wgsl_dir(&self) -> &Path {
+        &self.wgsl_dir
+    }
+
+    /// Files modified since the last call, relative to `wgsl_dir` and `/` separated, the same way
+    /// they are named by `#include`
+    pub fn changed_files(&self) -> Vec<String> {
+        let mut files = vec![];
+        for event in self.receiver.try_iter() {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    log::error!("shader watcher: {}", e);
+                    continue;
+                }
+            };
+            if event.kind.is_access() {
+                continue;
+            }
+            for path in event.paths {
+                // 编辑器保存时可能先删除再创建文件，此时无法 canonicalize
+                let path = path.canonicalize().unwrap_or(path);
+                if let Ok(relative) = path.strip_prefix(&self.wgsl_dir) {
+                    let name: Vec<_> = relative
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy())
+                        .collect();
+                    files.push(name.join("/"));
+                }
+            }
+        }
+        files.sort();
+        files.dedup();
+        files
+    }
+}
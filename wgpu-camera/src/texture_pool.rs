@@ -0,0 +1,54 @@
+pub(crate) struct PooledTexture {
+    // 只用来持有纹理，渲染与采样都通过 view
+    pub _texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub size: (u32, u32),
+}
+
+/// 可复用的中间渲染目标，避免滤镜链结构变化时反复创建纹理
+pub(crate) struct TexturePool {
+    format: wgpu::TextureFormat,
+    free: Vec<PooledTexture>,
+}
+
+impl TexturePool {
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        Self {
+            format,
+            free: vec![],
+        }
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    pub fn acquire(&mut self, device: &wgpu::Device, size: (u32, u32)) -> PooledTexture {
+        if let Some(index) = self.free.iter().position(|tex| tex.size == size) {
+            return self.free.swap_remove(index);
+        }
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("intermediate texture"),
+            size: wgpu::Extent3d {
+                width: size.0.max(1),
+                height: size.1.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        PooledTexture {
+            _texture: texture,
+            view,
+            size,
+        }
+    }
+
+    pub fn release(&mut self, texture: PooledTexture) {
+        self.free.push(texture);
+    }
+}
@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+
+// 查找表的宽度，每个输入值对应一个像素
+const LUT_WIDTH: usize = 256;
+
+/// A curve of `ToneCurve`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurveChannel {
+    /// Applied to all three channels, after their own curves
+    Master,
+    Red,
+    Green,
+    Blue,
+}
+
+/// Photoshop style curves, each a list of `(input, output)` control points in `0.0..=1.0`
+///
+/// The points are joined by a monotonic cubic spline, so the curve never overshoots between
+/// them; inputs outside of the first and last point keep their output. A curve with fewer than
+/// two points leaves its channel unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToneCurve {
+    pub master: Vec<(f32, f32)>,
+    pub red: Vec<(f32, f32)>,
+    pub green: Vec<(f32, f32)>,
+    pub blue: Vec<(f32, f32)>,
+}
+
+impl ToneCurve {
+    pub fn points(&self, channel: CurveChannel) -> &[(f32, f32)] {
+        match channel {
+            CurveChannel::Master => &self.master,
+            CurveChannel::Red => &self.red,
+            CurveChannel::Green => &self.green,
+            CurveChannel::Blue => &self.blue,
+        }
+    }
+
+    pub fn set_points(&mut self, channel: CurveChannel, points: Vec<(f32, f32)>) {
+        match channel {
+            CurveChannel::Master => self.master = points,
+            CurveChannel::Red => self.red = points,
+            CurveChannel::Green => self.green = points,
+            CurveChannel::Blue => self.blue = points,
+        }
+    }
+
+    /// A `LUT_WIDTH`x1 `Rgba8Unorm` texture, filled by `write_texture`
+    pub(crate) fn create_texture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> wgpu::Texture {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("tone curve texture"),
+            size: wgpu::Extent3d {
+                width: LUT_WIDTH as u32,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        self.write_texture(queue, &texture);
+        texture
+    }
+
+    /// Bake the curves into `texture`, each texel holds the red, green and blue outputs of one
+    /// input value
+    pub(crate) fn write_texture(&self, queue: &wgpu::Queue, texture: &wgpu::Texture) {
+        let master = bake_curve(&self.master);
+        let channels = [
+            bake_curve(&self.red),
+            bake_curve(&self.green),
+            bake_curve(&self.blue),
+        ];
+        let mut bytes = Vec::with_capacity(LUT_WIDTH * 4);
+        for i in 0..LUT_WIDTH {
+            for curve in channels.iter() {
+                // 先应用各通道的曲线，再应用主曲线
+                let value = sample_baked(&master, curve[i]);
+                bytes.push((value * 255.0).round() as u8);
+            }
+            bytes.push(255);
+        }
+        queue.write_texture(
+            texture.as_image_copy(),
+            &bytes,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(LUT_WIDTH as u32 * 4),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: LUT_WIDTH as u32,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+// 将曲线在 LUT_WIDTH 个等距的输入值上求值
+fn bake_curve(points: &[(f32, f32)]) -> Vec<f32> {
+    let inputs = (0..LUT_WIDTH).map(|i| i as f32 / (LUT_WIDTH - 1) as f32);
+    match MonotoneCubic::new(points) {
+        Some(spline) => inputs.map(|x| spline.eval(x)).collect(),
+        None => inputs.collect(),
+    }
+}
+
+// 在烘焙后的曲线上线性插值
+fn sample_baked(curve: &[f32], x: f32) -> f32 {
+    let pos = x.clamp(0.0, 1.0) * (LUT_WIDTH - 1) as f32;
+    let i = (pos.floor() as usize).min(LUT_WIDTH - 2);
+    let t = pos - i as f32;
+    curve[i] * (1.0 - t) + curve[i + 1] * t
+}
+
+/// Fritsch-Carlson monotonic cubic Hermite spline
+struct MonotoneCubic {
+    xs: Vec<f32>,
+    ys: Vec<f32>,
+    tangents: Vec<f32>,
+}
+
+impl MonotoneCubic {
+    fn new(points: &[(f32, f32)]) -> Option<Self> {
+        let mut points: Vec<(f32, f32)> = points
+            .iter()
+            .filter(|(x, y)| x.is_finite() && y.is_finite())
+            .map(|&(x, y)| (x.clamp(0.0, 1.0), y.clamp(0.0, 1.0)))
+            .collect();
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        // 输入相同的点只保留最后一个
+        let mut deduped: Vec<(f32, f32)> = Vec::with_capacity(points.len());
+        for point in points {
+            match deduped.last_mut() {
+                Some(last) if point.0 - last.0 < 1e-6 => *last = point,
+                _ => deduped.push(point),
+            }
+        }
+        if deduped.len() < 2 {
+            return None;
+        }
+        let xs: Vec<f32> = deduped.iter().map(|p| p.0).collect();
+        let ys: Vec<f32> = deduped.iter().map(|p| p.1).collect();
+        let n = xs.len();
+        let secants: Vec<f32> = (0..n - 1)
+            .map(|k| (ys[k + 1] - ys[k]) / (xs[k + 1] - xs[k]))
+            .collect();
+
+        let mut tangents = vec![0.0; n];
+        tangents[0] = secants[0];
+        tangents[n - 1] = secants[n - 2];
+        for k in 1..n - 1 {
+            // 斜率变号的点是极值点，切线为 0
+            if secants[k - 1] * secants[k] > 0.0 {
+                tangents[k] = (secants[k - 1] + secants[k]) / 2.0;
+            }
+        }
+        // 限制切线，保证每段内单调
+        for k in 0..n - 1 {
+            if secants[k] == 0.0 {
+                tangents[k] = 0.0;
+                tangents[k + 1] = 0.0;
+                continue;
+            }
+            let a = tangents[k] / secants[k];
+            let b = tangents[k + 1] / secants[k];
+            let s = a * a + b * b;
+            if s > 9.0 {
+                let t = 3.0 / s.sqrt();
+                tangents[k] = t * a * secants[k];
+                tangents[k + 1] = t * b * secants[k];
+            }
+        }
+        Some(Self { xs, ys, tangents })
+    }
+
+    fn eval(&self, x: f32) -> f32 {
+        let n = self.xs.len();
+        if x <= self.xs[0] {
+            return self.ys[0];
+        }
+        if x >= self.xs[n - 1] {
+            return self.ys[n - 1];
+        }
+        let k = self.xs.partition_point(|&xk| xk <= x).max(1) - 1;
+        let h = self.xs[k + 1] - self.xs[k];
+        let t = (x - self.xs[k]) / h;
+        let (t2, t3) = (t * t, t * t * t);
+        let y = (2.0 * t3 - 3.0 * t2 + 1.0) * self.ys[k]
+            + (t3 - 2.0 * t2 + t) * h * self.tangents[k]
+            + (-2.0 * t3 + 3.0 * t2) * self.ys[k + 1]
+            + (t3 - t2) * h * self.tangents[k + 1];
+        y.clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monotone_for_uneven_secants() {
+        // 斜率差别很大的递增点，普通的三次样条会在这里回摆
+        let points = [(0.0, 0.0), (0.1, 0.5), (0.2, 0.52), (0.8, 0.55), (1.0, 1.0)];
+        let curve = bake_curve(&points);
+        for pair in curve.windows(2) {
+            assert!(pair[1] >= pair[0] - 1e-6, "{} > {}", pair[0], pair[1]);
+        }
+        let spline = MonotoneCubic::new(&points).unwrap();
+        for (x, y) in points {
+            assert!((spline.eval(x) - y).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn no_overshoot_between_points() {
+        let points = [(0.0, 0.2), (0.3, 0.8), (0.5, 0.8), (0.7, 0.1), (1.0, 0.6)];
+        let spline = MonotoneCubic::new(&points).unwrap();
+        for segment in points.windows(2) {
+            let (lo, hi) = (
+                segment[0].1.min(segment[1].1),
+                segment[0].1.max(segment[1].1),
+            );
+            for i in 0..=100 {
+                let x = segment[0].0 + (segment[1].0 - segment[0].0) * i as f32 / 100.0;
+                let y = spline.eval(x);
+                assert!(y >= lo - 1e-6 && y <= hi + 1e-6, "f({}) = {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn degenerate_curves_are_identity() {
+        let identity = bake_curve(&[]);
+        assert_eq!(bake_curve(&[(0.5, 0.9)]), identity);
+        assert_eq!(bake_curve(&[(0.5, 0.9), (0.5, 0.1)]), identity);
+        assert!((sample_baked(&identity, 0.25) - 0.25).abs() < 1e-6);
+    }
+}
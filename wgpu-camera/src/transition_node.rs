@@ -0,0 +1,364 @@
+use crate::layout::DisplayUniform;
+use crate::page_turning::{PageCurl, PageTurningNode};
+use crate::texture_pool::{PooledTexture, TexturePool};
+use crate::{CompareDivider, Transition};
+use bytemuck::{Pod, Zeroable};
+use idroid::{
+    geometry::Plane,
+    vertex::{PosTex, Vertex},
+    BufferObj,
+};
+use wgpu::util::DeviceExt;
+use wgpu::{BindingType, BufferBindingType, ShaderModule, ShaderStages};
+
+/// `InputParams` of `transition.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct TransitionParams {
+    progress: f32,
+    kind: f32,
+    direction: [f32; 2],
+    aspect: f32,
+    feather: f32,
+}
+
+impl TransitionParams {
+    fn new(transition: Transition, progress: f32, aspect: f32) -> Self {
+        // direction 是 uv 空间中的推进方向，uv 的 y 轴向下
+        let (kind, direction) = match transition {
+            Transition::Crossfade => (0.0, [0.0, 0.0]),
+            Transition::WipeLeft => (1.0, [-1.0, 0.0]),
+            Transition::WipeRight => (1.0, [1.0, 0.0]),
+            Transition::WipeUp => (1.0, [0.0, -1.0]),
+            Transition::WipeDown => (1.0, [0.0, 1.0]),
+            Transition::CircularReveal => (2.0, [0.0, 0.0]),
+            // 由 PageTurningNode 绘制
+            Transition::PageCurl => (0.0, [0.0, 0.0]),
+        };
+        Self {
+            progress,
+            kind,
+            direction,
+            aspect,
+            feather: 0.05,
+        }
+    }
+
+    fn split(divider: CompareDivider, position: f32) -> Self {
+        Self {
+            progress: position,
+            kind: 3.0,
+            direction: divider.normal(),
+            aspect: 1.0,
+            feather: 0.0,
+        }
+    }
+}
+
+/// Composites the outgoing and incoming filter chains of a transition, or the original and
+/// filtered image of a comparison
+///
+/// Both chains are rendered into their own screen-sized texture, exactly as they would be drawn
+/// to the frame, and then blended into the frame by `transition.wgsl`, or by `PageTurningNode`
+/// for a page curl.
+pub(crate) struct TransitionNode {
+    sampler: wgpu::Sampler,
+    mvp_buf: BufferObj,
+    params_buf: BufferObj,
+    vertex_buf: BufferObj,
+    index_buf: wgpu::Buffer,
+    index_count: usize,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+    page_turning: PageTurningNode,
+    pool: TexturePool,
+    // (outgoing, incoming)
+    targets: Option<(PooledTexture, PooledTexture)>,
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+impl TransitionNode {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        shader_module: &ShaderModule,
+        page_turning_shader: &ShaderModule,
+    ) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        // 两个画面都已经应用过传感器旋转，合成时不再旋转
+        let (p_mat, vm_mat) =
+            idroid::utils::matrix_helper::perspective_fullscreen_mvp(config.into());
+        let mvp_uniform = DisplayUniform::new((p_mat * vm_mat).into());
+        let mvp_buf =
+            BufferObj::create_uniform_buffer(device, &mvp_uniform, Some("transition mvp"));
+        let params = TransitionParams::new(Transition::Crossfade, 0.0, 1.0);
+        let mut params_buf =
+            BufferObj::create_storage_buffer(device, &[params], Some("transition params"));
+        params_buf.read_only = true;
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(0),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(0),
+                    },
+                    count: None,
+                },
+                texture_entry(2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                texture_entry(4),
+            ],
+            label: Some("transition bind group layout"),
+        });
+
+        let (vertex_data, index_data) = Plane::new(1, 1).generate_vertices();
+        let vertex_buf = BufferObj::create_buffer(
+            device,
+            Some(&vertex_data),
+            None,
+            wgpu::BufferUsages::VERTEX,
+            Some("vertex buffer"),
+        );
+        let index_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("index buffer"),
+            contents: bytemuck::cast_slice(&index_data),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let vertex_attributes = PosTex::vertex_attributes(0);
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("transition pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: shader_module,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<PosTex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &vertex_attributes,
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader_module,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    // 两个画面已在 shader 中混合
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                front_face: wgpu::FrontFace::Ccw,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            sampler,
+            mvp_buf,
+            params_buf,
+            vertex_buf,
+            index_buf,
+            index_count: index_data.len(),
+            bind_group_layout,
+            pipeline,
+            page_turning: PageTurningNode::new(device, config.format, page_turning_shader),
+            pool: TexturePool::new(config.format),
+            targets: None,
+            bind_group: None,
+        }
+    }
+
+    /// Render targets of the `(outgoing, incoming)` chains, reallocated when `size` changes
+    pub fn targets(
+        &mut self,
+        device: &wgpu::Device,
+        size: (u32, u32),
+    ) -> (&wgpu::TextureView, &wgpu::TextureView) {
+        if self.targets.as_ref().map(|(from, _)| from.size) != Some(size) {
+            if let Some((from, to)) = self.targets.take() {
+                self.pool.release(from);
+                self.pool.release(to);
+            }
+            let from = self.pool.acquire(device, size);
+            let to = self.pool.acquire(device, size);
+            self.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: self.mvp_buf.buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: self.params_buf.buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(&to.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: wgpu::BindingResource::TextureView(&from.view),
+                    },
+                ],
+                label: Some("transition bind group"),
+            }));
+            self.page_turning.update_bind_group(
+                device,
+                &self.mvp_buf.buffer,
+                &self.sampler,
+                &from.view,
+                &to.view,
+                size,
+            );
+            self.targets = Some((from, to));
+        }
+        let (from, to) = self.targets.as_ref().unwrap();
+        (&from.view, &to.view)
+    }
+
+    /// Blend the two targets into `frame_view`, `progress` goes from 0 (outgoing) to 1 (incoming)
+    pub fn composite(
+        &self,
+        queue: &wgpu::Queue,
+        frame_view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+        transition: Transition,
+        progress: f32,
+    ) {
+        let size = match &self.targets {
+            Some((from, _)) => from.size,
+            None => return,
+        };
+        let aspect = size.0 as f32 / size.1.max(1) as f32;
+        let params = TransitionParams::new(transition, progress, aspect);
+        self.draw(queue, frame_view, encoder, params);
+    }
+
+    /// Show the outgoing target before the divider at `position` (0 to 1 across the surface)
+    /// and the incoming one after it
+    pub fn composite_split(
+        &self,
+        queue: &wgpu::Queue,
+        frame_view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+        divider: CompareDivider,
+        position: f32,
+    ) {
+        self.draw(
+            queue,
+            frame_view,
+            encoder,
+            TransitionParams::split(divider, position),
+        );
+    }
+
+    fn draw(
+        &self,
+        queue: &wgpu::Queue,
+        frame_view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+        params: TransitionParams,
+    ) {
+        let (bind_group, size) = match (&self.bind_group, &self.targets) {
+            (Some(bind_group), Some((from, _))) => (bind_group, from.size),
+            _ => return,
+        };
+        queue.write_buffer(&self.params_buf.buffer, 0, bytemuck::bytes_of(&params));
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("transition pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: frame_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, bind_group, &[]);
+        rpass.set_index_buffer(self.index_buf.slice(..), wgpu::IndexFormat::Uint32);
+        rpass.set_vertex_buffer(0, self.vertex_buf.buffer.slice(..));
+        rpass.set_viewport(0.0, 0.0, size.0 as f32, size.1 as f32, 0.0, 1.0);
+        rpass.draw_indexed(0..self.index_count as u32, 0, 0..1);
+    }
+
+    /// Curl the outgoing target away to reveal the incoming one
+    pub fn composite_page_curl(
+        &self,
+        queue: &wgpu::Queue,
+        frame_view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+        page_curl: PageCurl,
+        progress: f32,
+    ) {
+        let size = match &self.targets {
+            Some((from, _)) => from.size,
+            None => return,
+        };
+        let aspect = size.0 as f32 / size.1.max(1) as f32;
+        self.page_turning.draw(
+            queue,
+            frame_view,
+            encoder,
+            page_curl.corner(aspect, progress),
+        );
+    }
+}
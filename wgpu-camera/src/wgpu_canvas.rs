@@ -1,102 +1,793 @@
 use crate::{
-    fragment_filter_node::FragmentFilterNode, shader_manager::ShaderManager, FilterNode, FilterType,
+    canvas_target::{CanvasTarget, OffscreenTarget},
+    compute_filter_node::ComputeFilterNode,
+    filter_chain::{FilterChain, FilterStage, SourceTexture},
+    filter_params::{DitherParams, FilterParams, GaussianBlurParams, ToneCurveParams},
+    filter_schema::{builtin_filter_schemas, filter_schema, FilterSchema},
+    fragment_filter_node::FragmentFilterNode,
+    layer_compositor::{Layer, LayerCompositor},
+    layout::{
+        rotated_size, ContentLayout, ContentMode, CropRect, DisplayUniform, Mirror, ViewTransform,
+        UV_TRANSFORM_OFFSET,
+    },
+    lut::Lut3D,
+    page_turning::{page_position, PageCurl},
+    palette::Palette,
+    preset::{LayoutPreset, Preset, PresetError, StagePreset},
+    shader_manager::{ShaderError, ShaderManager},
+    tone_curve::ToneCurve,
+    transition_node::TransitionNode,
+    CompareDivider, FilterNode, FilterType, Transition,
 };
 use app_surface::{AppSurface, SurfaceFrame};
-use idroid::{BufferObj, MVPUniform};
+use idroid::BufferObj;
 use nalgebra_glm as glm;
+use std::collections::HashMap;
+
+// 过渡期间旧滤镜链继续渲染，直到新滤镜完全显示
+struct ActiveTransition {
+    outgoing: FilterChain,
+    transition: Transition,
+    duration: f32,
+    // 过渡开始后第一帧的时间戳
+    start: Option<f64>,
+    // 只用于 Transition::PageCurl
+    page_curl: PageCurl,
+}
+
+// 对比模式中原图的滤镜链与分割线
+struct Comparison {
+    original: FilterChain,
+    divider: CompareDivider,
+    // 0 ~ 1
+    position: f32,
+}
 
 pub struct WgpuCanvas {
-    pub app_surface: AppSurface,
+    target: CanvasTarget,
     shader_manager: ShaderManager,
     mvp_buffer: BufferObj,
-    params_buffer: BufferObj,
-    view_node: Option<Box<dyn FilterNode>>,
-    current_filter: FilterType,
+    chain: FilterChain,
+    transition: Option<ActiveTransition>,
+    transition_node: Option<TransitionNode>,
+    comparison: Option<Comparison>,
+    layers: LayerCompositor,
+    // 保留已绑定的外部纹理，滤镜链结构变化时需要重新绑定
+    sources: HashMap<String, SourceTexture>,
     img_size: (f32, f32),
+    viewport: (f32, f32, f32, f32),
+    content_mode: ContentMode,
+    source_crop: Option<CropRect>,
+    view_transform: ViewTransform,
+    preview_mirror: Mirror,
+    export_mirror: Mirror,
     opaque_background_color: bool,
+    // 相机传感器的旋转角度（度）
+    sensor_orientation: f32,
+    // 设备屏幕的旋转角度（度）
+    device_rotation: f32,
+    #[cfg(all(
+        feature = "hot-reload",
+        not(any(target_os = "ios", target_os = "android", target_arch = "wasm32"))
+    ))]
+    shader_watcher: Option<crate::shader_watcher::ShaderWatcher>,
 }
 
 #[allow(dead_code)]
 impl WgpuCanvas {
     pub fn new(app_surface: AppSurface) -> Self {
-        let shader_manager = ShaderManager::new(&app_surface.device);
-        let (p_mat, vm_mat) =
-            idroid::utils::matrix_helper::perspective_fullscreen_mvp((&app_surface.config).into());
-        let mvp_buffer = BufferObj::create_uniform_buffer(
-            &app_surface.device,
-            &MVPUniform {
-                mvp_matrix: (p_mat * vm_mat).into(),
-            },
-            Some("MVPUniformObj"),
-        );
+        Self::with_target(CanvasTarget::Surface(app_surface))
+    }
 
-        let storage_data = [0.0; 32];
-        let mut params_buffer = BufferObj::create_storage_buffer(
-            &app_surface.device,
-            &storage_data,
-            Some("Param Buffer"),
-        );
-        params_buffer.read_only = true;
+    /// Headless canvas that renders into an owned `size` texture of `format`
+    pub fn new_headless(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        size: (u32, u32),
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        Self::with_target(CanvasTarget::Offscreen(OffscreenTarget::new(
+            device, queue, size, format,
+        )))
+    }
+
+    /// Headless canvas on a fallback (software) adapter, for machines without a GPU
+    pub async fn request_headless(size: (u32, u32), format: wgpu::TextureFormat) -> Option<Self> {
+        let target = OffscreenTarget::request(size, format).await?;
+        Some(Self::with_target(CanvasTarget::Offscreen(target)))
+    }
+
+    fn with_target(target: CanvasTarget) -> Self {
+        let device = target.device();
+        let shader_manager = ShaderManager::new(device);
+        let (p_mat, vm_mat) =
+            idroid::utils::matrix_helper::perspective_fullscreen_mvp(target.config().into());
+        let mvp_uniform = DisplayUniform::new((p_mat * vm_mat).into());
+        let mvp_buffer =
+            BufferObj::create_uniform_buffer(device, &mvp_uniform, Some("MVPUniformObj"));
+        let plain_mvp_buffer =
+            BufferObj::create_uniform_buffer(device, &mvp_uniform, Some("plain MVPUniformObj"));
+        let chain = FilterChain::new(target.config().format, plain_mvp_buffer);
+        let layers = LayerCompositor::new(target.config().format);
 
         let instance = WgpuCanvas {
-            app_surface,
+            target,
             shader_manager,
             mvp_buffer,
-            params_buffer,
-            view_node: None,
-            current_filter: FilterType::AsciiArt,
+            chain,
+            transition: None,
+            transition_node: None,
+            comparison: None,
+            layers,
+            sources: HashMap::new(),
             img_size: (0.0, 0.0),
+            viewport: (0.0, 0.0, 0.0, 0.0),
+            content_mode: ContentMode::default(),
+            source_crop: None,
+            view_transform: ViewTransform::default(),
+            preview_mirror: Mirror::NONE,
+            export_mirror: Mirror::NONE,
             opaque_background_color: false,
+            sensor_orientation: 0.0,
+            device_rotation: 0.0,
+            #[cfg(all(
+                feature = "hot-reload",
+                not(any(target_os = "ios", target_os = "android", target_arch = "wasm32"))
+            ))]
+            shader_watcher: None,
         };
-        if let Some(callback) = instance.app_surface.callback_to_app {
+        if let Some(callback) = instance.app_surface().and_then(|s| s.callback_to_app) {
             callback(0);
         }
         instance
     }
 
+    /// The surface of a canvas created with `new`, `None` for a headless canvas
+    ///
+    /// This replaces the former public `app_surface` field: `canvas.app_surface.x` becomes
+    /// `canvas.app_surface().unwrap().x`, or `app_surface_mut()` for mutable access.
+    pub fn app_surface(&self) -> Option<&AppSurface> {
+        match &self.target {
+            CanvasTarget::Surface(app_surface) => Some(app_surface),
+            CanvasTarget::Offscreen(_) => None,
+        }
+    }
+
+    pub fn app_surface_mut(&mut self) -> Option<&mut AppSurface> {
+        match &mut self.target {
+            CanvasTarget::Surface(app_surface) => Some(app_surface),
+            CanvasTarget::Offscreen(_) => None,
+        }
+    }
+
+    pub fn device(&self) -> &wgpu::Device {
+        self.target.device()
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        self.target.queue()
+    }
+
+    /// The texture that a headless canvas renders into
+    pub fn offscreen_texture(&self) -> Option<&wgpu::Texture> {
+        match &self.target {
+            CanvasTarget::Surface(_) => None,
+            CanvasTarget::Offscreen(target) => Some(&target.texture),
+        }
+    }
+
+    /// Clockwise rotation of the camera sensor relative to the device's natural orientation,
+    /// in degrees
+    ///
+    /// For 90 and 270 degrees the image is laid out with its width and height swapped.
     pub fn set_camera_sensor_orientation(&mut self, angle: f32) {
-        let (p_mat, mut vm_mat) = idroid::utils::matrix_helper::perspective_fullscreen_mvp(
-            (&self.app_surface.config).into(),
-        );
-        vm_mat = glm::rotate(
-            &vm_mat,
-            angle / 180.0 * (-std::f32::consts::PI),
-            &glm::vec3(0.0, 0.0, 1.0),
-        );
+        self.sensor_orientation = angle;
+        self.update_layout();
+    }
 
-        let uniform: [[f32; 4]; 4] = (p_mat * vm_mat).into();
-        self.app_surface.queue.write_buffer(
-            &self.mvp_buffer.buffer,
-            0,
-            bytemuck::cast_slice(&uniform),
-        );
+    /// Clockwise rotation of the device's display from its natural orientation, in degrees,
+    /// subtracted from the sensor orientation so the image stays upright
+    pub fn set_device_rotation(&mut self, angle: f32) {
+        self.device_rotation = angle;
+        self.update_layout();
+    }
+
+    pub fn device_rotation(&self) -> f32 {
+        self.device_rotation
+    }
+
+    // 图像最终在屏幕上的顺时针旋转角度
+    fn display_rotation(&self) -> f32 {
+        self.sensor_orientation - self.device_rotation
     }
 
+    /// Mirror the preview, e.g. horizontally for a front-facing camera
+    pub fn set_preview_mirror(&mut self, mirror: Mirror) {
+        self.preview_mirror = mirror;
+        self.write_mvp(false);
+    }
+
+    pub fn preview_mirror(&self) -> Mirror {
+        self.preview_mirror
+    }
+
+    /// Mirror the frames returned by `capture_frame`, independently of the preview
+    ///
+    /// A selfie can be mirrored in the preview but saved unmirrored.
+    pub fn set_export_mirror(&mut self, mirror: Mirror) {
+        self.export_mirror = mirror;
+    }
+
+    pub fn export_mirror(&self) -> Mirror {
+        self.export_mirror
+    }
+
+    /// Zoom by `scale`, pan by `translate` (in surface pixels) and rotate clockwise by
+    /// `rotation` degrees around the center of the preview
+    ///
+    /// In `AspectFill` and `Stretch` modes the transform is clamped so that the image keeps
+    /// covering the surface; in `AspectFit` mode the image is clipped to its letterboxed area.
+    /// Returns the transform that was applied.
+    pub fn set_view_transform(
+        &mut self,
+        scale: f32,
+        translate: (f32, f32),
+        rotation: f32,
+    ) -> ViewTransform {
+        self.view_transform = ViewTransform {
+            scale,
+            translate,
+            rotation,
+        };
+        self.update_layout();
+        self.view_transform
+    }
+
+    pub fn view_transform(&self) -> ViewTransform {
+        self.view_transform
+    }
+
+    /// Map a position in surface pixels (y down), such as a tap, to pixels of the source
+    /// texture, `None` if the position is outside the image
+    pub fn view_to_image(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        let layout = self.layout();
+        let (px, py) = self.view_transform.to_model((x, y), layout.viewport)?;
+        let (sx, sy) = self.preview_mirror.scale();
+        let (px, py) = (px * sx, py * sy);
+        // 撤销图像的旋转
+        let (sin, cos) = self.display_rotation().to_radians().sin_cos();
+        let mx = px * cos - py * sin;
+        let my = px * sin + py * cos;
+        // 平面的 [-1, 1] 对应纹理坐标 [0, 1]，纹理的 v 轴向下
+        let u = (mx + 1.0) / 2.0;
+        let v = (1.0 - my) / 2.0;
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return None;
+        }
+        Some((
+            (layout.uv_offset[0] + u * layout.uv_scale[0]) * self.img_size.0,
+            (layout.uv_offset[1] + v * layout.uv_scale[1]) * self.img_size.1,
+        ))
+    }
+
+    /// How the source image is fitted into the surface, `AspectFill` by default
+    pub fn set_content_mode(&mut self, mode: ContentMode) {
+        self.content_mode = mode;
+        self.update_layout();
+    }
+
+    pub fn content_mode(&self) -> ContentMode {
+        self.content_mode
+    }
+
+    /// Only show the `crop` rectangle of the source texture, `None` shows all of it
+    ///
+    /// The content mode fits the cropped image, and `capture_frame` returns it at its own
+    /// resolution.
+    pub fn set_source_crop(&mut self, crop: Option<CropRect>) {
+        self.source_crop = crop;
+        self.update_layout();
+    }
+
+    pub fn source_crop(&self) -> Option<CropRect> {
+        self.source_crop
+    }
+
+    /// Replace the whole filter chain with a single `ty` stage
     pub fn set_filter(
         &mut self,
         ty: crate::FilterType,
         opaque_background_color: bool,
         input_param: f32,
     ) {
-        self.create_render_node_if_needed();
+        self.finish_transition();
         self.opaque_background_color = opaque_background_color;
-        self.view_node.as_mut().map(|node| {
-            node.change_filter(&self.app_surface, self.shader_manager.get_shader_ref(ty));
-            self.current_filter = ty;
+        // compute 滤镜的管线与节点类型绑定，只有 fragment 滤镜之间可以直接替换管线；
+        // 带辅助纹理的滤镜的 bind group 布局不同，也需要重建节点
+        let old = self.chain.stages.first().map(|stage| stage.filter);
+        let can_change_in_place = self.chain.stages.len() == 1
+            && old.map_or(false, |old| {
+                !old.is_compute() && old.aux_texture_dimension().is_none()
+            })
+            && !ty.is_compute()
+            && ty.aux_texture_dimension().is_none();
+        if can_change_in_place {
+            let target = &self.target;
+            let stage = &mut self.chain.stages[0];
+            if let Err(e) = stage.node.change_filter(
+                target.device(),
+                target.config().format,
+                self.shader_manager.get_shader_ref(ty),
+            ) {
+                log::error!("set_filter({:?}): {}", ty, e);
+            }
+            stage.filter = ty;
+            stage.custom_name = None;
+        } else {
+            self.chain.stages.clear();
+            let stage = self.create_stage(ty);
+            self.chain.insert(0, stage);
+            self.rebind_sources();
+        }
+        self.change_stage_param(0, input_param);
+    }
+
+    /// Replace the whole filter chain with a single `ty` stage, blending from the current chain
+    /// to the new one over `duration` seconds
+    ///
+    /// The transition advances with the timestamps passed to `enter_frame`, starting at the next
+    /// frame. The new stage uses the schema default of its param, `change_filter_param` updates it.
+    ///
+    /// The filter changes immediately while a comparison is shown.
+    pub fn set_filter_animated(&mut self, ty: FilterType, duration: f32, transition: Transition) {
+        if duration <= 0.0 || self.chain.stages.is_empty() || self.comparison.is_some() {
+            self.set_filter(ty, self.opaque_background_color, 0.0);
+            return;
+        }
+        // 前一个过渡直接结束，从当前显示的滤镜开始新的过渡
+        self.finish_transition();
+        let outgoing = self.chain.split_off(self.target.config().format);
+        let stage = self.create_stage(ty);
+        self.chain.insert(0, stage);
+        self.transition = Some(ActiveTransition {
+            outgoing,
+            transition,
+            duration,
+            start: None,
+            page_curl: PageCurl::Auto,
         });
-        self.update_filter_params(input_param);
+        self.rebind_sources();
+        self.change_stage_param(0, 0.0);
     }
 
-    pub fn change_filter_param(&self, input_param: f32) {
-        self.update_filter_params(input_param);
+    /// Start a `Transition::PageCurl` to `ty` whose page follows `drag_page_curl` until
+    /// `end_page_curl`
+    pub fn begin_page_curl(&mut self, ty: FilterType) {
+        self.set_filter_animated(ty, 1.0, Transition::PageCurl);
+        if let Some(transition) = &mut self.transition {
+            let config = self.target.config();
+            let aspect = config.width as f32 / config.height as f32;
+            transition.page_curl = PageCurl::Dragging([aspect, -1.0]);
+        }
     }
 
-    pub fn set_external_sampler(&mut self, sampler: wgpu::Sampler) {
-        self.view_node.as_mut().map(|node| {
-            node.update_sampler(sampler);
+    /// Move the dragged corner of the page to the touch position, in surface pixels
+    pub fn drag_page_curl(&mut self, x: f32, y: f32) {
+        let config = self.target.config();
+        if let Some(transition) = &mut self.transition {
+            if let PageCurl::Dragging(_) = transition.page_curl {
+                let size = (config.width as f32, config.height as f32);
+                transition.page_curl = PageCurl::Dragging(page_position(x, y, size));
+            }
+        }
+    }
+
+    /// Release the page, it finishes curling away over `duration` seconds once it has been
+    /// dragged past the middle of the surface, otherwise it falls back and the old filter is kept
+    pub fn end_page_curl(&mut self, duration: f32) {
+        if let Some(transition) = &mut self.transition {
+            if let PageCurl::Dragging(from) = transition.page_curl {
+                transition.page_curl = PageCurl::Released {
+                    from,
+                    cancel: from[0] > 0.0,
+                };
+                transition.duration = duration.max(f32::EPSILON);
+                transition.start = None;
+            }
+        }
+    }
+
+    pub fn is_transitioning(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    /// Show the `Original` image on one side of `divider` and the active filter on the other,
+    /// both sampling the texture key passed to `enter_frame`; `None` ends the comparison
+    pub fn set_comparison(&mut self, divider: Option<CompareDivider>) {
+        let divider = match divider {
+            Some(divider) => divider,
+            None => {
+                if let Some(comparison) = self.comparison.take() {
+                    self.chain.recycle(comparison.original);
+                }
+                return;
+            }
+        };
+        if let Some(comparison) = &mut self.comparison {
+            comparison.divider = divider;
+            return;
+        }
+        self.finish_transition();
+        self.comparison = Some(Comparison {
+            original: self.create_comparison_chain(),
+            divider,
+            position: 0.5,
+        });
+        self.rebind_sources();
+    }
+
+    /// Move the divider, `position` goes from 0 (only the filter is shown) to 1 (only the
+    /// original is shown)
+    pub fn set_comparison_position(&mut self, position: f32) {
+        if let Some(comparison) = &mut self.comparison {
+            comparison.position = position.clamp(0.0, 1.0);
+        }
+    }
+
+    /// Move the divider through a touch position, in surface pixels
+    pub fn drag_comparison(&mut self, x: f32, y: f32) {
+        let config = self.target.config();
+        if let Some(comparison) = &mut self.comparison {
+            let normal = comparison.divider.normal();
+            let uv = (x / config.width as f32, y / config.height as f32);
+            comparison.position = (uv.0 * normal[0] + uv.1 * normal[1]).clamp(0.0, 1.0);
+        }
+    }
+
+    /// Draw several textures, each with its own filter, rectangle and z order, in a single
+    /// render pass, e.g. picture-in-picture or a grid of filters (`Layer::grid`)
+    ///
+    /// While layers are set `enter_frame` draws them instead of the filter chain, its `tex_key`
+    /// is ignored. Layers with a custom filter are skipped; an empty list ends the compositing.
+    pub fn set_layers(&mut self, layers: Vec<Layer>) {
+        let mut reusable = self.layers.take_stages();
+        for layer in layers {
+            let ty = layer.params.filter_type();
+            if ty == FilterType::Custom {
+                continue;
+            }
+            // 复用滤镜相同的 stage，避免重新创建管线
+            let mut stage = match reusable.iter().position(|stage| stage.filter == ty) {
+                Some(i) => reusable.swap_remove(i),
+                None => self.create_stage(ty),
+            };
+            stage.set_params(self.target.queue(), layer.params.clone());
+            self.layers.push(self.target.device(), layer, stage);
+        }
+        self.rebind_layer_sources();
+    }
+
+    /// Add a layer on top of the current ones, see `set_layers`
+    pub fn add_layer(&mut self, layer: Layer) {
+        let mut layers: Vec<Layer> = self.layers.layers().cloned().collect();
+        layers.push(layer);
+        self.set_layers(layers);
+    }
+
+    pub fn layers(&self) -> Vec<Layer> {
+        self.layers.layers().cloned().collect()
+    }
+
+    /// The divider and its position while comparing
+    pub fn comparison(&self) -> Option<(CompareDivider, f32)> {
+        self.comparison
+            .as_ref()
+            .map(|comparison| (comparison.divider, comparison.position))
+    }
+
+    /// Validate a fragment filter shader and make it available to `set_custom_filter`
+    ///
+    /// `common/group0+vs.wgsl` is included before `wgsl_source` unless the source includes it
+    /// itself; the default `InputParams` receives the filter param and the opaque background flag.
+    /// Registering an existing name again replaces the shader of the stages that use it. Names
+    /// with `/`, `\`, `"` or `,` are rejected with `ShaderError::InvalidName`.
+    ///
+    /// A shader that declares bindings other than 0 ~ 3 of group 0, or whose `fs_main` does not
+    /// return `@location(0) vec4<f32>`, is rejected with `ShaderError::Pipeline`. This check is
+    /// skipped on wasm32.
+    pub fn register_filter(&mut self, name: &str, wgsl_source: &str) -> Result<(), ShaderError> {
+        self.shader_manager.register_custom(
+            self.target.device(),
+            self.target.config().format,
+            name,
+            wgsl_source,
+        )?;
+        let shader = self.shader_manager.get_custom_shader(name).unwrap();
+        for stage in self.chain.stages.iter_mut() {
+            if stage.custom_name.as_deref() == Some(name) {
+                // register_custom 已经用同样的布局检查过 shader
+                stage.node.change_filter(
+                    self.target.device(),
+                    self.target.config().format,
+                    shader,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the whole filter chain with the registered filter `name`
+    ///
+    /// Returns false if no filter was registered with that name.
+    pub fn set_custom_filter(
+        &mut self,
+        name: &str,
+        opaque_background_color: bool,
+        input_param: f32,
+    ) -> bool {
+        let stage = match self.create_custom_stage(name) {
+            Some(stage) => stage,
+            None => return false,
+        };
+        self.finish_transition();
+        self.opaque_background_color = opaque_background_color;
+        self.chain.stages.clear();
+        self.chain.insert(0, stage);
+        self.rebind_sources();
+        self.change_stage_param(0, input_param);
+        true
+    }
+
+    pub fn insert_custom_filter(&mut self, index: usize, name: &str, input_param: f32) -> bool {
+        let stage = match self.create_custom_stage(name) {
+            Some(stage) => stage,
+            None => return false,
+        };
+        let index = index.min(self.chain.stages.len());
+        self.chain.insert(index, stage);
+        self.rebind_sources();
+        self.change_stage_param(index, input_param);
+        true
+    }
+
+    /// Grade the `FilterType::Lut3D` stage at `index` with `lut`
+    ///
+    /// Returns false if the stage at `index` is not a `Lut3D` stage.
+    pub fn set_lut(&mut self, index: usize, lut: &Lut3D) -> bool {
+        let texture = match self.chain.stages.get(index) {
+            Some(stage) if stage.filter == FilterType::Lut3D => {
+                lut.create_texture(self.target.device(), self.target.queue())
+            }
+            _ => return false,
+        };
+        self.chain.stages[index].node.set_aux_texture(texture);
+        self.rebind_sources();
+        true
+    }
+
+    /// Registered name of the `FilterType::Custom` stage at `index`
+    pub fn custom_filter_name(&self, index: usize) -> Option<&str> {
+        self.chain.stages.get(index)?.custom_name.as_deref()
+    }
+
+    /// Update the parameter of the last stage, the one drawn to the screen
+    pub fn change_filter_param(&mut self, input_param: f32) {
+        if let Some(index) = self.chain.stages.len().checked_sub(1) {
+            self.change_stage_param(index, input_param);
+        }
+    }
+
+    pub fn change_stage_param(&mut self, index: usize, input_param: f32) {
+        let scale_factor = self.target.scale_factor();
+        if let Some(stage) = self.chain.stages.get_mut(index) {
+            stage
+                .params
+                .apply_input_param(input_param, scale_factor, self.opaque_background_color);
+            self.update_filter_params(index);
+        }
+    }
+
+    /// Set every parameter of the stage at `index`
+    ///
+    /// Returns false if there is no such stage or `params` belongs to another filter type.
+    pub fn set_filter_params(&mut self, index: usize, params: FilterParams) -> bool {
+        match self.chain.stages.get_mut(index) {
+            Some(stage) if stage.filter == params.filter_type() => {
+                stage.set_params(self.target.queue(), params);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn filter_params(&self, index: usize) -> Option<&FilterParams> {
+        Some(&self.chain.stages.get(index)?.params)
+    }
+
+    /// Replace the curves of the `ToneCurve` stage at `index`, they are baked into its lookup
+    /// texture only if they changed
+    ///
+    /// Returns false if the stage at `index` is not a `ToneCurve` stage.
+    pub fn set_tone_curve(&mut self, index: usize, curves: ToneCurve) -> bool {
+        match self.filter_params(index) {
+            Some(FilterParams::ToneCurve(params)) => {
+                let params = ToneCurveParams {
+                    curves,
+                    ..params.clone()
+                };
+                self.set_filter_params(index, FilterParams::ToneCurve(params))
+            }
+            _ => false,
+        }
+    }
+
+    /// Set the input black point, white point and gamma of the `ToneCurve` stage at `index`
+    pub fn set_levels(
+        &mut self,
+        index: usize,
+        black_point: f32,
+        white_point: f32,
+        gamma: f32,
+    ) -> bool {
+        match self.filter_params(index) {
+            Some(FilterParams::ToneCurve(params)) => {
+                let params = ToneCurveParams {
+                    black_point,
+                    white_point,
+                    gamma,
+                    ..params.clone()
+                };
+                self.set_filter_params(index, FilterParams::ToneCurve(params))
+            }
+            _ => false,
+        }
+    }
+
+    /// Replace the palette of the `Dither` stage at `index`, an empty palette quantizes each
+    /// channel to `DitherParams::levels` levels
+    ///
+    /// Returns false if the stage at `index` is not a `Dither` stage.
+    pub fn set_dither_palette(&mut self, index: usize, palette: Palette) -> bool {
+        match self.filter_params(index) {
+            Some(FilterParams::Dither(params)) => {
+                let params = DitherParams {
+                    palette,
+                    ..params.clone()
+                };
+                self.set_filter_params(index, FilterParams::Dither(params))
+            }
+            _ => false,
+        }
+    }
+
+    /// Set the radius (in pixels) and sigma of a `GaussianBlur` stage, a `sigma` of 0 is
+    /// derived from the radius
+    ///
+    /// Returns false if the stage at `index` is not a `GaussianBlur` stage.
+    pub fn set_gaussian_blur(&mut self, index: usize, radius: f32, sigma: f32) -> bool {
+        self.set_filter_params(
+            index,
+            FilterParams::GaussianBlur(GaussianBlurParams { radius, sigma }),
+        )
+    }
+
+    /// Schemas of the built-in filters followed by the registered custom filters, for building
+    /// the filter selection and parameter UI
+    pub fn filter_schemas(&self) -> Vec<FilterSchema> {
+        let scale_factor = self.target.scale_factor();
+        let mut schemas = builtin_filter_schemas(scale_factor);
+        for name in self.shader_manager.custom_names() {
+            let mut schema = filter_schema(FilterType::Custom, scale_factor);
+            schema.name = name.to_string();
+            schemas.push(schema);
+        }
+        schemas
+    }
+
+    /// Snapshot of the filter chain, its parameters and the canvas settings
+    pub fn export_preset(&self, name: &str) -> Preset {
+        let mut preset = Preset::new(name);
+        preset.opaque_background_color = self.opaque_background_color;
+        preset.sensor_orientation = Some(self.sensor_orientation);
+        preset.layout = Some(LayoutPreset {
+            content_mode: self.content_mode,
+            device_rotation: self.device_rotation,
+            source_crop: self.source_crop,
+            view_transform: self.view_transform,
+            preview_mirror: self.preview_mirror,
+            export_mirror: self.export_mirror,
         });
+        preset.stages = self
+            .chain
+            .stages
+            .iter()
+            .map(|stage| StagePreset::new(&stage.params, stage.custom_name.as_deref()))
+            .collect();
+        preset
+    }
+
+    /// Replace the filter chain and canvas settings with `preset`
+    ///
+    /// Nothing is changed if the preset uses a custom filter that is not registered.
+    pub fn apply_preset(&mut self, preset: &Preset) -> Result<(), PresetError> {
+        let mut stages = vec![];
+        for stage_preset in preset.stages.iter() {
+            let mut stage = match stage_preset.custom_name() {
+                Some(name) => self
+                    .create_custom_stage(name)
+                    .ok_or_else(|| PresetError::UnknownCustomFilter(name.to_string()))?,
+                None => self.create_stage(stage_preset.filter_type()),
+            };
+            stage.set_params(self.target.queue(), stage_preset.params());
+            stages.push(stage);
+        }
+        self.finish_transition();
+        self.opaque_background_color = preset.opaque_background_color;
+        if let Some(angle) = preset.sensor_orientation {
+            self.set_camera_sensor_orientation(angle);
+        }
+        if let Some(layout) = &preset.layout {
+            self.content_mode = layout.content_mode;
+            self.device_rotation = layout.device_rotation;
+            self.source_crop = layout.source_crop;
+            self.view_transform = layout.view_transform;
+            self.preview_mirror = layout.preview_mirror;
+            self.export_mirror = layout.export_mirror;
+            self.update_layout();
+        }
+        self.chain.stages.clear();
+        for (i, stage) in stages.into_iter().enumerate() {
+            self.chain.insert(i, stage);
+        }
+        self.rebind_sources();
+        Ok(())
+    }
+
+    /// Filters of the chain, in the order they are applied
+    pub fn filters(&self) -> Vec<FilterType> {
+        self.chain.filters()
+    }
+
+    pub fn push_filter(&mut self, ty: FilterType, input_param: f32) {
+        let index = self.chain.stages.len();
+        self.insert_filter(index, ty, input_param);
+    }
+
+    pub fn insert_filter(&mut self, index: usize, ty: FilterType, input_param: f32) {
+        let index = index.min(self.chain.stages.len());
+        let stage = self.create_stage(ty);
+        self.chain.insert(index, stage);
+        self.rebind_sources();
+        self.change_stage_param(index, input_param);
+    }
+
+    pub fn remove_filter(&mut self, index: usize) -> Option<FilterType> {
+        let stage = self.chain.remove(index)?;
+        self.rebind_sources();
+        Some(stage.filter)
+    }
+
+    pub fn move_filter(&mut self, from: usize, to: usize) {
+        if self.chain.move_stage(from, to) {
+            self.rebind_sources();
+        }
     }
 
+    pub fn set_external_sampler(&mut self, sampler: wgpu::Sampler) {
+        self.chain.set_external_sampler(sampler);
+        // 原图的滤镜链需要共享新的 sampler
+        if self.comparison.is_some() {
+            let original = self.create_comparison_chain();
+            if let Some(comparison) = &mut self.comparison {
+                let old = std::mem::replace(&mut comparison.original, original);
+                self.chain.recycle(old);
+            }
+        }
+        self.rebind_sources();
+    }
+
+    /// `external_texture` is expected to have a non-sRGB format, like the camera frames, see
+    /// `set_external_texture_with_format`
     pub fn set_external_texture(
         &mut self,
         external_texture: wgpu::Texture,
@@ -106,6 +797,25 @@ impl WgpuCanvas {
         self.set_external_tv(external_texture, None, tex_key, img_size);
     }
 
+    /// Like `set_external_texture` for a texture of any format, filters that work in linear
+    /// light need to know whether the GPU already decodes its sRGB colors when sampling it
+    pub fn set_external_texture_with_format(
+        &mut self,
+        external_texture: wgpu::Texture,
+        format: wgpu::TextureFormat,
+        tex_key: String,
+        img_size: (f32, f32),
+    ) {
+        let view = external_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.bind_external_texture(
+            external_texture,
+            view,
+            format.describe().srgb,
+            tex_key,
+            img_size,
+        );
+    }
+
     pub fn set_external_tv(
         &mut self,
         external_texture: wgpu::Texture,
@@ -113,125 +823,533 @@ impl WgpuCanvas {
         tex_key: String,
         img_size: (f32, f32),
     ) {
-        self.img_size = img_size;
-        let sw = self.app_surface.config.width as f32;
-        let sh = self.app_surface.config.height as f32;
-        let w_ratio = sw / img_size.0;
-        let h_ratio = sh / img_size.1;
-        let viewport = if w_ratio > h_ratio {
-            let h = img_size.1 * w_ratio;
-            (0.0, (sh - h) / 2.0, sw, h)
-        } else {
-            let w = img_size.0 * h_ratio;
-            ((sw - w) / 2.0, 0.0, w, sh)
+        let view = match external_tv {
+            Some(tv) => tv,
+            None => external_texture.create_view(&wgpu::TextureViewDescriptor::default()),
         };
+        self.bind_external_texture(external_texture, view, false, tex_key, img_size);
+    }
+
+    fn bind_external_texture(
+        &mut self,
+        external_texture: wgpu::Texture,
+        view: wgpu::TextureView,
+        srgb: bool,
+        tex_key: String,
+        img_size: (f32, f32),
+    ) {
+        self.img_size = img_size;
+        self.update_layout();
         self.create_render_node_if_needed();
-        self.view_node.as_mut().map(|node| {
-            node.update_viewport(viewport);
-            node.update_bind_group(
-                &self.app_surface,
+
+        let source = SourceTexture {
+            _texture: external_texture,
+            view,
+            srgb,
+            size: (img_size.0 as u32, img_size.1 as u32),
+        };
+        self.chain.bind_source(
+            self.target.device(),
+            self.target.queue(),
+            &self.mvp_buffer.buffer,
+            &tex_key,
+            &source,
+        );
+        if let Some(transition) = &mut self.transition {
+            transition.outgoing.bind_source(
+                self.target.device(),
+                self.target.queue(),
                 &self.mvp_buffer.buffer,
-                &self.params_buffer.buffer,
-                &external_texture,
-                external_tv,
-                tex_key,
-            )
-        });
+                &tex_key,
+                &source,
+            );
+        }
+        if let Some(comparison) = &mut self.comparison {
+            comparison.original.bind_source(
+                self.target.device(),
+                self.target.queue(),
+                &self.mvp_buffer.buffer,
+                &tex_key,
+                &source,
+            );
+        }
+        self.layers.bind_source(
+            self.target.device(),
+            self.target.queue(),
+            &tex_key,
+            &source,
+            self.chain.external_sampler(),
+        );
+        self.sources.insert(tex_key, source);
     }
 
     pub fn remove_texture(&mut self, tex_key: String) {
-        self.view_node
-            .as_mut()
-            .map(|node| node.remove_bind_group(tex_key));
+        self.chain.remove_source(&tex_key);
+        if let Some(transition) = &mut self.transition {
+            transition.outgoing.remove_source(&tex_key);
+        }
+        if let Some(comparison) = &mut self.comparison {
+            comparison.original.remove_source(&tex_key);
+        }
+        self.layers.remove_source(&tex_key);
+        self.sources.remove(&tex_key);
     }
 
-    pub fn enter_frame(&mut self, tex_key: String) {
-        if let Some(view_node) = &mut self.view_node {
-            let device = &self.app_surface.device;
-            let queue = &self.app_surface.queue;
-            let (frame, view) = self.app_surface.get_current_frame_view();
-            let mut encoder =
-                device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-            {
-                view_node.enter_frame(&view, &mut encoder, tex_key);
+    /// Draw a frame of `tex_key`, `timestamp` (in seconds, from any monotonic clock) advances
+    /// a running `set_filter_animated` transition
+    pub fn enter_frame(&mut self, tex_key: String, timestamp: f64) {
+        #[cfg(all(
+            feature = "hot-reload",
+            not(any(target_os = "ios", target_os = "android", target_arch = "wasm32"))
+        ))]
+        self.reload_changed_shaders();
+        let progress = self.advance_transition(timestamp);
+        if !self.chain.stages.is_empty() || !self.layers.is_empty() {
+            let mut encoder = self
+                .target
+                .device()
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            let (frame, view) = match &self.target {
+                CanvasTarget::Surface(app_surface) => {
+                    let (frame, view) = app_surface.get_current_frame_view();
+                    (Some(frame), view)
+                }
+                CanvasTarget::Offscreen(target) => (
+                    None,
+                    target
+                        .texture
+                        .create_view(&wgpu::TextureViewDescriptor::default()),
+                ),
+            };
+            if self.layers.is_empty() {
+                self.draw_frame(&view, &mut encoder, &tex_key, progress);
+            } else {
+                self.draw_layers(&view, &mut encoder);
+            }
+            self.target.queue().submit(Some(encoder.finish()));
+            if let Some(frame) = frame {
+                frame.present();
             }
-            queue.submit(Some(encoder.finish()));
-            frame.present();
         }
 
-        if let Some(_callback) = self.app_surface.callback_to_app {
+        if let Some(_callback) = self.app_surface().and_then(|s| s.callback_to_app) {
             // callback(1);
         }
     }
 
+    /// Render the active filter for `tex_key` at image resolution and read it back as
+    /// tightly packed RGBA8 rows, returns `(pixels, width, height)`
+    pub fn capture_frame(&mut self, tex_key: String) -> Option<(Vec<u8>, u32, u32)> {
+        if self.chain.stages.is_empty() {
+            return None;
+        }
+        // 按裁剪并旋转后的图像分辨率输出
+        let uv_scale = self.layout().uv_scale;
+        let (width, height) = rotated_size(
+            (
+                (self.img_size.0 * uv_scale[0]).round() as u32,
+                (self.img_size.1 * uv_scale[1]).round() as u32,
+            ),
+            self.display_rotation(),
+        );
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let device = self.target.device();
+        let queue = self.target.queue();
+        let format = self.target.config().format;
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("capture texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        self.chain
+            .set_viewport((0.0, 0.0, width as f32, height as f32));
+        self.chain.enter_frame(&view, &mut encoder, &tex_key);
+        self.chain.set_viewport(self.viewport);
+        // 预览的缩放、平移、旋转与镜像不应用到输出的图像上
+        self.write_mvp(true);
+        queue.submit(Some(encoder.finish()));
+        self.write_mvp(false);
+
+        let pixels =
+            crate::readback::read_texture_rgba8(device, queue, &texture, (width, height), format)?;
+        Some((pixels, width, height))
+    }
+
+    pub fn capture_image(&mut self, tex_key: String) -> Option<image::RgbaImage> {
+        let (pixels, width, height) = self.capture_frame(tex_key)?;
+        image::RgbaImage::from_raw(width, height, pixels)
+    }
+
     pub fn resize(&mut self) {
-        self.app_surface.resize_surface();
+        if let Some(app_surface) = self.app_surface_mut() {
+            app_surface.resize_surface();
+        }
+        self.update_layout();
     }
 
-    fn create_render_node_if_needed(&mut self) {
-        if self.view_node.is_none() {
-            let filter_type = FilterType::Original;
-            let node = FragmentFilterNode::new(
-                &self.app_surface,
-                &self.shader_manager.get_shader_ref(filter_type),
+    /// Reallocate the headless target, no-op for a surface canvas
+    pub fn resize_offscreen(&mut self, size: (u32, u32)) {
+        if let CanvasTarget::Offscreen(target) = &mut self.target {
+            target.resize(size);
+        }
+        self.update_layout();
+    }
+
+    /// Watch `wgsl_dir` (the repo's `wgsl/` directory) and rebuild the built-in filters whose
+    /// shader files change, checked at the start of every `enter_frame`
+    #[cfg(all(
+        feature = "hot-reload",
+        not(any(target_os = "ios", target_os = "android", target_arch = "wasm32"))
+    ))]
+    pub fn watch_shaders<P: AsRef<std::path::Path>>(&mut self, wgsl_dir: P) -> notify::Result<()> {
+        self.shader_watcher = Some(crate::shader_watcher::ShaderWatcher::new(
+            wgsl_dir.as_ref(),
+        )?);
+        Ok(())
+    }
+
+    #[cfg(all(
+        feature = "hot-reload",
+        not(any(target_os = "ios", target_os = "android", target_arch = "wasm32"))
+    ))]
+    fn reload_changed_shaders(&mut self) {
+        use crate::shader_manager::ShaderUse;
+
+        let watcher = match &self.shader_watcher {
+            Some(watcher) => watcher,
+            None => return,
+        };
+        let changed_files = watcher.changed_files();
+        if changed_files.is_empty() {
+            return;
+        }
+        let device = self.target.device();
+        let changed = self.shader_manager.reload_builtin(
+            device,
+            self.target.config(),
+            watcher.wgsl_dir(),
+            &changed_files,
+        );
+        if changed.contains(&ShaderUse::Transition) {
+            // 下次过渡或对比时用新的 shader 重建
+            self.transition_node = None;
+        }
+        let mut rebuilt = vec![];
+        for i in 0..self.chain.stages.len() {
+            let ty = self.chain.stages[i].filter;
+            if ty.is_compute() {
+                // compute 节点无法替换管线，连同显示用的 original shader 一起重建
+                if changed.contains(&ShaderUse::Filter(ty))
+                    || changed.contains(&ShaderUse::Filter(FilterType::Original))
+                {
+                    let mut stage = self.create_stage(ty);
+                    stage.params = self.chain.stages[i].params.clone();
+                    self.chain.stages[i] = stage;
+                    rebuilt.push(i);
+                }
+            } else if changed.contains(&ShaderUse::Filter(ty)) {
+                if let Err(e) = self.chain.stages[i].node.change_filter(
+                    device,
+                    self.target.config().format,
+                    self.shader_manager.get_shader_ref(ty),
+                ) {
+                    log::error!("{:?}: {}", ty, e);
+                }
+            }
+        }
+        if !rebuilt.is_empty() {
+            self.rebind_sources();
+            for i in rebuilt {
+                self.update_filter_params(i);
+            }
+        }
+    }
+
+    fn draw_frame(
+        &mut self,
+        view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+        tex_key: &str,
+        progress: Option<f32>,
+    ) {
+        if progress.is_none() && self.comparison.is_none() {
+            self.chain.enter_frame(view, encoder, tex_key);
+            return;
+        }
+        let device = self.target.device();
+        let config = self.target.config();
+        let shader_manager = &self.shader_manager;
+        let node = self.transition_node.get_or_insert_with(|| {
+            TransitionNode::new(
+                device,
+                config,
+                &shader_manager.transition,
+                &shader_manager.page_turning,
+            )
+        });
+        let (from_view, to_view) = node.targets(device, (config.width, config.height));
+        self.chain.enter_frame(to_view, encoder, tex_key);
+        let queue = self.target.queue();
+        if let (Some(transition), Some(progress)) = (&mut self.transition, progress) {
+            transition.outgoing.enter_frame(from_view, encoder, tex_key);
+            if transition.transition == Transition::PageCurl {
+                node.composite_page_curl(queue, view, encoder, transition.page_curl, progress);
+            } else {
+                node.composite(queue, view, encoder, transition.transition, progress);
+            }
+        } else if let Some(comparison) = &mut self.comparison {
+            comparison.original.enter_frame(from_view, encoder, tex_key);
+            node.composite_split(
+                queue,
+                view,
+                encoder,
+                comparison.divider,
+                comparison.position,
             );
+        }
+    }
 
-            self.view_node = Some(Box::new(node));
-            self.current_filter = filter_type;
+    fn draw_layers(&mut self, view: &wgpu::TextureView, encoder: &mut wgpu::CommandEncoder) {
+        let rotation = self.display_rotation();
+        let config = self.target.config();
+        let (p_mat, vm_mat) =
+            idroid::utils::matrix_helper::perspective_fullscreen_mvp(config.into());
+        self.layers.enter_frame(
+            self.target.queue(),
+            view,
+            encoder,
+            p_mat * vm_mat,
+            rotation,
+            (config.width, config.height),
+        );
+    }
+
+    // 返回过渡进度，过渡结束后返回 None
+    fn advance_transition(&mut self, timestamp: f64) -> Option<f32> {
+        let transition = self.transition.as_mut()?;
+        // 拖动中的页面只跟随触摸位置
+        if let PageCurl::Dragging(_) = transition.page_curl {
+            return Some(0.0);
+        }
+        let start = *transition.start.get_or_insert(timestamp);
+        let t = ((timestamp - start) / transition.duration as f64) as f32;
+        if t >= 1.0 {
+            if let PageCurl::Released { cancel: true, .. } = transition.page_curl {
+                self.cancel_transition();
+            } else {
+                self.finish_transition();
+            }
+            return None;
+        }
+        // 缓入缓出
+        let t = t.max(0.0);
+        Some(t * t * (3.0 - 2.0 * t))
+    }
+
+    fn finish_transition(&mut self) {
+        if let Some(transition) = self.transition.take() {
+            self.chain.recycle(transition.outgoing);
         }
     }
 
-    fn update_filter_params(&self, input_param: f32) {
-        let opaque_background_color = if self.opaque_background_color {
-            1.0
+    // 回到过渡之前的滤镜链
+    fn cancel_transition(&mut self) {
+        if let Some(transition) = self.transition.take() {
+            let incoming = std::mem::replace(&mut self.chain, transition.outgoing);
+            self.chain.recycle(incoming);
+        }
+    }
+
+    fn layout(&self) -> ContentLayout {
+        let config = self.target.config();
+        ContentLayout::new(
+            self.content_mode,
+            self.source_crop,
+            self.img_size,
+            self.display_rotation(),
+            (config.width as f32, config.height as f32),
+        )
+    }
+
+    // 图像尺寸、surface 尺寸、旋转角度、ContentMode 或裁剪区域变化后，重新计算 viewport、uv 变换与 mvp
+    fn update_layout(&mut self) {
+        let layout = self.layout();
+        let config = self.target.config();
+        self.view_transform = self.view_transform.clamped(
+            self.content_mode,
+            layout.viewport,
+            (config.width as f32, config.height as f32),
+        );
+        self.viewport = layout.viewport;
+        self.chain.set_viewport(layout.viewport);
+        if let Some(transition) = &mut self.transition {
+            transition.outgoing.set_viewport(layout.viewport);
+        }
+        if let Some(comparison) = &mut self.comparison {
+            comparison.original.set_viewport(layout.viewport);
+        }
+        // 裁剪只在绘制到 surface 的最后一个 stage 上应用，中间 pass 处理完整的源图像
+        self.target.queue().write_buffer(
+            &self.mvp_buffer.buffer,
+            UV_TRANSFORM_OFFSET,
+            bytemuck::cast_slice(&layout.uv_transform()),
+        );
+        self.write_mvp(false);
+    }
+
+    // 图像旋转之后依次应用镜像与预览的变换
+    fn write_mvp(&self, for_export: bool) {
+        let (p_mat, vm_mat) =
+            idroid::utils::matrix_helper::perspective_fullscreen_mvp(self.target.config().into());
+        let rotation = glm::rotation(
+            self.display_rotation() / 180.0 * (-std::f32::consts::PI),
+            &glm::vec3(0.0, 0.0, 1.0),
+        );
+        let mirror = if for_export {
+            self.export_mirror
         } else {
-            0.0
+            self.preview_mirror
         };
-        let params_data = match self.current_filter {
-            FilterType::AsciiArt => {
-                let ascii_width = if input_param == 0.0 {
-                    8.0 * self.app_surface.scale_factor
-                } else {
-                    input_param.min(64.0).max(8.0)
-                };
-                vec![
-                    1.0 / self.img_size.0 * ascii_width,
-                    1.0 / self.img_size.1 * ascii_width,
-                    ascii_width,
-                    ascii_width / 2.0,
-                ]
-            }
-            FilterType::CrossHatch => {
-                let density = if input_param == 0.0 {
-                    10.0 * self.app_surface.scale_factor
-                } else {
-                    input_param.min(64.0).max(10.0)
-                };
-                vec![
-                    density,
-                    density / 2.0,
-                    density * 0.08,
-                    0.8,
-                    0.6,
-                    0.3,
-                    0.15,
-                    opaque_background_color,
-                ]
-            }
-            FilterType::EdgeDetection => {
-                let noise_suppression = if input_param == 0.0 {
-                    0.05
-                } else {
-                    input_param.min(0.33).max(0.05)
-                };
-                vec![noise_suppression, opaque_background_color]
+        let (sx, sy) = mirror.scale();
+        let mut model = glm::scaling(&glm::vec3(sx, sy, 1.0)) * rotation;
+        if !for_export {
+            model = self.view_transform.model_matrix(self.viewport) * model;
+        }
+        let uniform: [[f32; 4]; 4] = (p_mat * vm_mat * model).into();
+        self.target.queue().write_buffer(
+            &self.mvp_buffer.buffer,
+            0,
+            bytemuck::cast_slice(&uniform),
+        );
+    }
+
+    fn create_render_node_if_needed(&mut self) {
+        if self.chain.stages.is_empty() {
+            let stage = self.create_stage(FilterType::Original);
+            self.chain.insert(0, stage);
+        }
+    }
+
+    fn create_stage(&self, ty: FilterType) -> FilterStage {
+        let device = self.target.device();
+        let config = self.target.config();
+        let viewport_size = (config.width as f32, config.height as f32);
+        let node: Box<dyn FilterNode> = if ty.is_compute() {
+            Box::new(ComputeFilterNode::new(
+                device,
+                config.format,
+                viewport_size,
+                &self.shader_manager.get_compute_shaders(ty),
+                self.shader_manager.get_shader_ref(FilterType::Original),
+                ty.compute_scratch_rows(),
+            ))
+        } else {
+            let mut node = FragmentFilterNode::new(
+                device,
+                config.format,
+                viewport_size,
+                self.shader_manager.get_shader_ref(ty),
+                ty.aux_texture_dimension(),
+            );
+            // 加载 LUT 之前画面保持不变，曲线纹理与默认参数一致
+            match ty {
+                FilterType::Lut3D => node.set_aux_texture(
+                    Lut3D::identity(2).create_texture(device, self.target.queue()),
+                ),
+                FilterType::ToneCurve => node.set_aux_texture(
+                    ToneCurve::default().create_texture(device, self.target.queue()),
+                ),
+                _ => {}
             }
-            _ => vec![0.0],
+            Box::new(node)
         };
-        self.app_surface.queue.write_buffer(
-            &self.params_buffer.buffer,
-            0,
-            bytemuck::cast_slice(&params_data),
+        FilterStage::new(device, ty, node)
+    }
+
+    fn create_custom_stage(&self, name: &str) -> Option<FilterStage> {
+        let shader = self.shader_manager.get_custom_shader(name)?;
+        let device = self.target.device();
+        let config = self.target.config();
+        let node = FragmentFilterNode::new(
+            device,
+            config.format,
+            (config.width as f32, config.height as f32),
+            shader,
+            None,
         );
+        let mut stage = FilterStage::new(device, FilterType::Custom, Box::new(node));
+        stage.custom_name = Some(name.to_string());
+        Some(stage)
+    }
+
+    fn rebind_sources(&mut self) {
+        for (tex_key, source) in self.sources.iter() {
+            self.chain.bind_source(
+                self.target.device(),
+                self.target.queue(),
+                &self.mvp_buffer.buffer,
+                tex_key,
+                source,
+            );
+            if let Some(transition) = &mut self.transition {
+                transition.outgoing.bind_source(
+                    self.target.device(),
+                    self.target.queue(),
+                    &self.mvp_buffer.buffer,
+                    tex_key,
+                    source,
+                );
+            }
+            if let Some(comparison) = &mut self.comparison {
+                comparison.original.bind_source(
+                    self.target.device(),
+                    self.target.queue(),
+                    &self.mvp_buffer.buffer,
+                    tex_key,
+                    source,
+                );
+            }
+        }
+        self.rebind_layer_sources();
+    }
+
+    fn rebind_layer_sources(&mut self) {
+        for (tex_key, source) in self.sources.iter() {
+            self.layers.bind_source(
+                self.target.device(),
+                self.target.queue(),
+                tex_key,
+                source,
+                self.chain.external_sampler(),
+            );
+        }
+    }
+
+    // 只有一个 Original stage 的滤镜链，用于对比模式
+    fn create_comparison_chain(&self) -> FilterChain {
+        let mut chain = self.chain.fork(self.target.config().format);
+        let stage = self.create_stage(FilterType::Original);
+        stage.write_params(self.target.queue());
+        chain.insert(0, stage);
+        chain
+    }
+
+    fn update_filter_params(&self, stage_index: usize) {
+        if let Some(stage) = self.chain.stages.get(stage_index) {
+            stage.write_params(self.target.queue());
+        }
     }
 }
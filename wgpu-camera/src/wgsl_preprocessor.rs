@@ -0,0 +1,477 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+/// An error produced while preprocessing a WGSL source
+#[derive(Debug, Clone, PartialEq)]
+pub enum PreprocessError {
+    /// The root source could not be found in memory or on disk
+    NotFound(String),
+    /// `#include` of a module that could not be found
+    MissingInclude {
+        name: String,
+        file: String,
+        line: usize,
+    },
+    /// The include chain, starting and ending with the same module
+    IncludeCycle(Vec<String>),
+    /// `#else`/`#endif` without an opening `#ifdef`, or an `#ifdef` that is never closed
+    UnbalancedConditional { file: String, line: usize },
+    InvalidDirective {
+        file: String,
+        line: usize,
+        text: String,
+    },
+}
+
+impl std::fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(name) => write!(f, "can't find shader source \"{}\"", name),
+            Self::MissingInclude { name, file, line } => {
+                write!(f, "{}:{}: can't find include \"{}\"", file, line, name)
+            }
+            Self::IncludeCycle(chain) => write!(f, "include cycle: {}", chain.join(" -> ")),
+            Self::UnbalancedConditional { file, line } => {
+                write!(f, "{}:{}: unbalanced conditional block", file, line)
+            }
+            Self::InvalidDirective { file, line, text } => {
+                write!(f, "{}:{}: invalid directive `{}`", file, line, text)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PreprocessError {}
+
+/// Original file and 1-based line of a line in the preprocessed output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: usize,
+}
+
+impl std::fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+pub struct PreprocessedShader {
+    pub code: String,
+    files: Vec<String>,
+    // 输出的每一行对应的 (files 下标, 原始行号)
+    line_map: Vec<(usize, usize)>,
+}
+
+impl PreprocessedShader {
+    /// Every module the code was assembled from, in the order they were included
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    /// Map a 1-based line of `code` back to the file it came from
+    pub fn source_location(&self, line: usize) -> Option<SourceLocation> {
+        let (file, line) = *self.line_map.get(line.checked_sub(1)?)?;
+        Some(SourceLocation {
+            file: self.files[file].clone(),
+            line,
+        })
+    }
+
+    /// Map a byte offset into `code` (e.g. the start of a naga span) back to the file it came from
+    pub fn location_of_offset(&self, offset: usize) -> Option<SourceLocation> {
+        let offset = offset.min(self.code.len());
+        let line = self.code.as_bytes()[..offset]
+            .iter()
+            .filter(|b| **b == b'\n')
+            .count();
+        self.source_location(line + 1)
+    }
+}
+
+struct Conditional {
+    active: bool,
+    parent_active: bool,
+    has_else: bool,
+    line: usize,
+}
+
+/// Runtime version of the `///#include` preprocessor used by build.rs
+///
+/// Sources are looked up among the in-memory modules first and then under `base_dir`.
+/// Supported directives (with either a `#` or a `///#` prefix):
+/// `include "a.wgsl", "b.wgsl"`, `define NAME [value]`, `undef NAME`, `ifdef NAME`,
+/// `ifndef NAME`, `else` and `endif`. Every module is included at most once.
+#[derive(Default)]
+pub struct WgslPreprocessor {
+    base_dir: Option<PathBuf>,
+    sources: HashMap<String, String>,
+    defines: HashMap<String, String>,
+}
+
+impl WgslPreprocessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_base_dir<P: Into<PathBuf>>(mut self, base_dir: P) -> Self {
+        self.base_dir = Some(base_dir.into());
+        self
+    }
+
+    /// Register an in-memory module that can be `#include`d by `name`
+    pub fn add_source(&mut self, name: &str, code: &str) {
+        self.sources.insert(name.to_string(), code.to_string());
+    }
+
+    /// A define whose value is not empty also replaces the identifier `name` in the code
+    pub fn define(&mut self, name: &str, value: &str) {
+        self.defines.insert(name.to_string(), value.to_string());
+    }
+
+    pub fn undefine(&mut self, name: &str) {
+        self.defines.remove(name);
+    }
+
+    /// Preprocess the module `name`, loaded the same way as includes
+    pub fn process(&self, name: &str) -> Result<PreprocessedShader, PreprocessError> {
+        let code = self
+            .load(name)
+            .ok_or_else(|| PreprocessError::NotFound(name.to_string()))?;
+        self.process_source(name, &code)
+    }
+
+    /// Preprocess `code`; `name` is only used for error reporting and line mapping
+    pub fn process_source(
+        &self,
+        name: &str,
+        code: &str,
+    ) -> Result<PreprocessedShader, PreprocessError> {
+        let mut state = State {
+            defines: self.defines.clone(),
+            included: HashSet::new(),
+            stack: vec![],
+            output: PreprocessedShader {
+                code: String::new(),
+                files: vec![],
+                line_map: vec![],
+            },
+        };
+        self.parse(name, code, &mut state)?;
+        Ok(state.output)
+    }
+
+    fn load(&self, name: &str) -> Option<String> {
+        if let Some(code) = self.sources.get(name) {
+            return Some(code.clone());
+        }
+        let path = self.base_dir.as_ref()?.join(name);
+        std::fs::read_to_string(path).ok()
+    }
+
+    fn parse(&self, name: &str, code: &str, state: &mut State) -> Result<(), PreprocessError> {
+        if state.stack.iter().any(|n| n == name) {
+            let mut chain = state.stack.clone();
+            chain.push(name.to_string());
+            return Err(PreprocessError::IncludeCycle(chain));
+        }
+        state.included.insert(name.to_string());
+        state.stack.push(name.to_string());
+        state.output.files.push(name.to_string());
+        let file_index = state.output.files.len() - 1;
+
+        let mut conditionals: Vec<Conditional> = vec![];
+        for (i, line) in code.lines().enumerate() {
+            let line_number = i + 1;
+            let active = conditionals.last().map_or(true, |c| c.active);
+            let directive = match parse_directive(line) {
+                Some(directive) => directive,
+                None => {
+                    if active {
+                        state.push_line(line, file_index, line_number);
+                    }
+                    continue;
+                }
+            };
+            let invalid = || PreprocessError::InvalidDirective {
+                file: name.to_string(),
+                line: line_number,
+                text: line.trim().to_string(),
+            };
+            let unbalanced = || PreprocessError::UnbalancedConditional {
+                file: name.to_string(),
+                line: line_number,
+            };
+            let (keyword, rest) = match directive.split_once(char::is_whitespace) {
+                Some((keyword, rest)) => (keyword, rest.trim()),
+                None => (directive, ""),
+            };
+            match keyword {
+                "ifdef" | "ifndef" => {
+                    if rest.is_empty() {
+                        return Err(invalid());
+                    }
+                    let defined = state.defines.contains_key(rest);
+                    conditionals.push(Conditional {
+                        active: active && (defined == (keyword == "ifdef")),
+                        parent_active: active,
+                        has_else: false,
+                        line: line_number,
+                    });
+                }
+                "else" => match conditionals.last_mut() {
+                    Some(c) if !c.has_else => {
+                        c.has_else = true;
+                        c.active = c.parent_active && !c.active;
+                    }
+                    _ => return Err(unbalanced()),
+                },
+                "endif" => {
+                    if conditionals.pop().is_none() {
+                        return Err(unbalanced());
+                    }
+                }
+                _ if !active => {}
+                "define" => {
+                    let (key, value) = match rest.split_once(char::is_whitespace) {
+                        Some((key, value)) => (key, value.trim()),
+                        None => (rest, ""),
+                    };
+                    if key.is_empty() {
+                        return Err(invalid());
+                    }
+                    state.defines.insert(key.to_string(), value.to_string());
+                }
+                "undef" => {
+                    state.defines.remove(rest);
+                }
+                "include" => {
+                    for import in rest.split(',') {
+                        let import = import.trim().trim_matches('"');
+                        if import.is_empty() {
+                            return Err(invalid());
+                        }
+                        // 每个模块只引入一次，相当于自带 include guard
+                        if state.included.contains(import)
+                            && !state.stack.iter().any(|n| n == import)
+                        {
+                            continue;
+                        }
+                        let include =
+                            self.load(import)
+                                .ok_or_else(|| PreprocessError::MissingInclude {
+                                    name: import.to_string(),
+                                    file: name.to_string(),
+                                    line: line_number,
+                                })?;
+                        self.parse(import, &include, state)?;
+                    }
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        if let Some(c) = conditionals.last() {
+            return Err(PreprocessError::UnbalancedConditional {
+                file: name.to_string(),
+                line: c.line,
+            });
+        }
+        state.stack.pop();
+        Ok(())
+    }
+}
+
+struct State {
+    defines: HashMap<String, String>,
+    included: HashSet<String>,
+    stack: Vec<String>,
+    output: PreprocessedShader,
+}
+
+impl State {
+    fn push_line(&mut self, line: &str, file_index: usize, line_number: usize) {
+        substitute_defines(line, &self.defines, &mut self.output.code);
+        self.output.code.push('\n');
+        self.output.line_map.push((file_index, line_number));
+    }
+}
+
+/// Whether `code` has an `#include` directive of `module`, conditional blocks are not evaluated
+pub(crate) fn includes_module(code: &str, module: &str) -> bool {
+    code.lines().filter_map(parse_directive).any(|directive| {
+        directive
+            .strip_prefix("include")
+            .filter(|rest| rest.starts_with(char::is_whitespace))
+            .map_or(false, |rest| {
+                rest.split(',')
+                    .any(|import| import.trim().trim_matches('"') == module)
+            })
+    })
+}
+
+fn parse_directive(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    let rest = line
+        .strip_prefix("///#")
+        .or_else(|| line.strip_prefix('#'))?;
+    Some(rest.trim_end())
+}
+
+fn substitute_defines(line: &str, defines: &HashMap<String, String>, output: &mut String) {
+    if defines.values().all(|value| value.is_empty()) {
+        output.push_str(line);
+        return;
+    }
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut rest = line;
+    while let Some(start) = rest.find(is_ident) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(|c: char| !is_ident(c)).unwrap_or(rest.len());
+        let ident = &rest[..end];
+        match defines.get(ident) {
+            Some(value) if !value.is_empty() => output.push_str(value),
+            _ => output.push_str(ident),
+        }
+        rest = &rest[end..];
+    }
+    output.push_str(rest);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preprocessor(sources: &[(&str, &str)]) -> WgslPreprocessor {
+        let mut preprocessor = WgslPreprocessor::new();
+        for (name, code) in sources {
+            preprocessor.add_source(name, code);
+        }
+        preprocessor
+    }
+
+    #[test]
+    fn include_cycle_is_an_error() {
+        let p = preprocessor(&[
+            ("a.wgsl", "#include \"b.wgsl\""),
+            ("b.wgsl", "///#include \"a.wgsl\""),
+        ]);
+        assert_eq!(
+            p.process("a.wgsl").err(),
+            Some(PreprocessError::IncludeCycle(vec![
+                "a.wgsl".to_string(),
+                "b.wgsl".to_string(),
+                "a.wgsl".to_string(),
+            ]))
+        );
+    }
+
+    #[test]
+    fn module_included_twice_is_emitted_once() {
+        let p = preprocessor(&[
+            ("common.wgsl", "let SHARED: f32 = 1.0;"),
+            ("func.wgsl", "#include \"common.wgsl\"\nfn f() {}"),
+        ]);
+        let shader = p
+            .process_source(
+                "root",
+                "#include \"common.wgsl\", \"func.wgsl\"\n#include \"common.wgsl\"",
+            )
+            .unwrap();
+        assert_eq!(shader.code, "let SHARED: f32 = 1.0;\nfn f() {}\n");
+        assert_eq!(shader.files(), ["root", "common.wgsl", "func.wgsl"]);
+    }
+
+    #[test]
+    fn missing_sources_are_errors() {
+        let p = preprocessor(&[]);
+        assert_eq!(
+            p.process("none.wgsl").err(),
+            Some(PreprocessError::NotFound("none.wgsl".to_string()))
+        );
+        assert_eq!(
+            p.process_source("root", "\n#include \"none.wgsl\"").err(),
+            Some(PreprocessError::MissingInclude {
+                name: "none.wgsl".to_string(),
+                file: "root".to_string(),
+                line: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn conditional_blocks() {
+        let code =
+            "#ifdef FAST\nlet a = 1;\n#else\nlet a = 2;\n#endif\n#ifndef FAST\nlet b = 3;\n#endif";
+        let mut p = preprocessor(&[]);
+        assert_eq!(
+            p.process_source("root", code).unwrap().code,
+            "let a = 2;\nlet b = 3;\n"
+        );
+        p.define("FAST", "");
+        assert_eq!(p.process_source("root", code).unwrap().code, "let a = 1;\n");
+
+        // 定义可以写在 shader 中，嵌套在未生效的块中的指令被忽略
+        let code =
+            "#define FAST\n#ifdef SLOW\n#undef FAST\n#endif\n#ifdef FAST\nlet c = 4;\n#endif";
+        assert_eq!(
+            preprocessor(&[]).process_source("root", code).unwrap().code,
+            "let c = 4;\n"
+        );
+
+        assert_eq!(
+            preprocessor(&[]).process_source("root", "#ifdef A\n").err(),
+            Some(PreprocessError::UnbalancedConditional {
+                file: "root".to_string(),
+                line: 1,
+            })
+        );
+        assert_eq!(
+            preprocessor(&[]).process_source("root", "#else").err(),
+            Some(PreprocessError::UnbalancedConditional {
+                file: "root".to_string(),
+                line: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn defines_replace_whole_identifiers_only() {
+        let mut p = preprocessor(&[]);
+        p.define("N", "4u");
+        let shader = p
+            .process_source(
+                "root",
+                "let N_MAX = N + MY_N + N2;\nlet m = array<f32, N>();",
+            )
+            .unwrap();
+        assert_eq!(
+            shader.code,
+            "let N_MAX = 4u + MY_N + N2;\nlet m = array<f32, 4u>();\n"
+        );
+    }
+
+    #[test]
+    fn offsets_map_back_to_the_included_file() {
+        let p = preprocessor(&[("inc.wgsl", "// inc 1\nfn inc() {}\n// inc 3")]);
+        let shader = p
+            .process_source("root", "// root 1\n#include \"inc.wgsl\"\nfn root() {}")
+            .unwrap();
+        let location = |needle: &str| shader.location_of_offset(shader.code.find(needle).unwrap());
+        assert_eq!(
+            location("fn inc"),
+            Some(SourceLocation {
+                file: "inc.wgsl".to_string(),
+                line: 2,
+            })
+        );
+        assert_eq!(
+            location("fn root"),
+            Some(SourceLocation {
+                file: "root".to_string(),
+                line: 3,
+            })
+        );
+        assert_eq!(shader.source_location(1).unwrap().file, "root");
+    }
+}
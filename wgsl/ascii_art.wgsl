@@ -1,8 +1,6 @@
 struct InputParams {
-    // ascii_width_uv = (1.0 / img_size) * ascii_width;
-    ascii_width_uv: vec2<f32>,
-    ascii_width: f32,
-    half_aw: f32,
+    // 字符格子的边长（源纹理像素）
+    cell_size: f32,
 };
 
 ///#include "common/group0+vs.wgsl"
@@ -22,7 +20,8 @@ fn character(n: i32, p: vec2<f32>) -> f32 {
 @fragment
 fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
     let params = params[0];
-    let uv = floor(vertex.uv / params.ascii_width_uv) * params.ascii_width_uv;
+    let cell_uv = vec2<f32>(params.cell_size) / vec2<f32>(textureDimensions(tex));
+    let uv = floor(vertex.uv / cell_uv) * cell_uv;
     let color = textureSample(tex, tex_sampler, uv).rgb;
     let gray = length(color);
 
@@ -35,6 +34,6 @@ fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
     if (gray > 0.7) { n = 4532799; }  // ∆
 	if (gray > 0.8) { n = 11512810; } // #
 	
-    let p = ((vertex.position.xy / params.half_aw) % 2.0) - vec2<f32>(1.0);
+    let p = ((vertex.position.xy / (params.cell_size * 0.5)) % 2.0) - vec2<f32>(1.0);
     return vec4<f32>(color * character(n, p), 1.0);
 }
\ No newline at end of file
@@ -0,0 +1,48 @@
+// dither.wgsl 与 dither_diffusion.wgsl 共用的参数与绑定
+struct DitherParams {
+    // 输出像素块的边长（源纹理像素）
+    pixel_size: f32,
+    // 0: Bayer，1: 蓝噪声，2: Floyd-Steinberg，3: Atkinson
+    method: f32,
+    // Bayer 矩阵的边长：2, 4 或 8
+    bayer_size: f32,
+    // 没有调色板时每个通道的色阶数
+    levels: f32,
+    // 有序抖动的阈值偏移幅度
+    spread: f32,
+    palette_len: f32,
+    // 每个 u32 是一个打包的 rgb8 颜色，r 在最低字节
+    palette: array<u32, 58>,
+};
+
+@group(0) @binding(0) var<storage, read> params: DitherParams;
+// 误差扩散的环形缓冲区，有序抖动不使用
+@group(0) @binding(1) var<storage, read_write> diffusion_errors: array<vec4<f32>>;
+@group(0) @binding(2) var output_tex: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(3) var input_tex: texture_2d<f32>;
+
+fn unpack_color(c: u32) -> vec3<f32> {
+    return vec3<f32>(f32(c & 0xffu), f32((c >> 8u) & 0xffu), f32((c >> 16u) & 0xffu)) / 255.0;
+}
+
+// 最接近的调色板颜色，没有调色板时按色阶量化
+fn quantize(color: vec3<f32>) -> vec3<f32> {
+    let len = u32(params.palette_len);
+    if (len == 0u) {
+        let steps = max(round(params.levels), 2.0) - 1.0;
+        return clamp(floor(color * steps + 0.5) / steps, vec3<f32>(0.0), vec3<f32>(1.0));
+    }
+    // 按人眼对各通道的敏感度加权的距离
+    let weights = vec3<f32>(0.299, 0.587, 0.114);
+    var best = unpack_color(params.palette[0]);
+    var best_distance = dot((color - best) * (color - best), weights);
+    for (var i = 1u; i < len; i = i + 1u) {
+        let candidate = unpack_color(params.palette[i]);
+        let distance = dot((color - candidate) * (color - candidate), weights);
+        if (distance < best_distance) {
+            best = candidate;
+            best_distance = distance;
+        }
+    }
+    return best;
+}
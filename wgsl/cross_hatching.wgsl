@@ -1,14 +1,11 @@
 struct InputParams {
     // How close together hatch lines should be placed
     density: f32,
-    half_density: f32,
     // How wide hatch lines are drawn.
-    width: f32,
+    line_width: f32,
     // The brightnesses at which different hatch lines appear
-    hatch_1: f32,
-    hatch_2: f32,
-    hatch_3: f32,
-    hatch_4: f32,
+    thresholds: array<f32, 4>,
+    edge_noise_suppression: f32,
     opaque_background_color: f32,
 };
 
@@ -21,23 +18,24 @@ struct InputParams {
 fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
     let color = textureSample(tex, tex_sampler, vertex.uv);
     let gray = length(color.rgb);
-    let black_edge = 1.0 - edge_detection(gray, 0.125);
+    let black_edge = 1.0 - edge_detection(gray, params[0].edge_noise_suppression);
 
     var frag_color = vec3<f32>(1.0);
     let frag_coord = vertex.position.xy;
 
     let param = params[0];
-    if (gray < param.hatch_1) && ((frag_coord.x + frag_coord.y) % param.density <= param.width) {
+    let half_density = param.density * 0.5;
+    if (gray < param.thresholds[0]) && ((frag_coord.x + frag_coord.y) % param.density <= param.line_width) {
         frag_color = vec3<f32>(gray);
     }
-    if (gray < param.hatch_2) && (abs((frag_coord.x - frag_coord.y)) % param.density <= param.width) {
+    if (gray < param.thresholds[1]) && (abs((frag_coord.x - frag_coord.y)) % param.density <= param.line_width) {
         frag_color = vec3<f32>(gray);
     }
      
-    if (gray < param.hatch_3) && (abs((frag_coord.x + frag_coord.y - param.half_density)) % param.density <= param.width) {
+    if (gray < param.thresholds[2]) && (abs((frag_coord.x + frag_coord.y - half_density)) % param.density <= param.line_width) {
         frag_color = vec3<f32>(gray);
     }
-    if (gray < param.hatch_4) && (abs((frag_coord.x - frag_coord.y - param.half_density)) % param.density <= param.width) {
+    if (gray < param.thresholds[3]) && (abs((frag_coord.x - frag_coord.y - half_density)) % param.density <= param.line_width) {
         frag_color = vec3<f32>(0.0);
     }
 
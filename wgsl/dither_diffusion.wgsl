@@ -0,0 +1,73 @@
+///#include "common/dither.wgsl"
+
+// 误差扩散时同时处理的行数，即 workgroup 的大小
+let ROWS: u32 = 256u;
+// diffusion_errors 中的环形行数，每行的误差最多扩散到下面两行
+// 由 shader_defines.rs 注入，与 FilterType::compute_scratch_rows 使用同一个值
+let ERROR_ROWS: u32 = DITHER_ERROR_ROWS;
+
+fn add_error(x: i32, row: u32, cells: vec2<u32>, error: vec3<f32>) {
+    if (x < 0 || u32(x) >= cells.x || row >= cells.y) {
+        return;
+    }
+    let i = (row % ERROR_ROWS) * cells.x + u32(x);
+    diffusion_errors[i] = diffusion_errors[i] + vec4<f32>(error, 0.0);
+}
+
+// 误差扩散时才执行，且只 dispatch 一个 workgroup：每个线程处理一行，每行比上一行落后两个像素块，
+// 处理一个像素块时，上一行扩散给它的误差都已写入
+@compute @workgroup_size(16, 16)
+fn cs_main(@builtin(local_invocation_index) local_index: u32) {
+    let img_size = vec2<u32>(textureDimensions(input_tex));
+    let pixel_size = u32(max(params.pixel_size, 1.0));
+    let cells = (img_size + pixel_size - 1u) / pixel_size;
+    let atkinson = u32(params.method) == 3u;
+    let lag = 2u * local_index;
+    for (var block = 0u; block < cells.y; block = block + ROWS) {
+        let row = block + local_index;
+        // 同一行内向右扩散的误差保存在寄存器中
+        var carry1 = vec3<f32>(0.0);
+        var carry2 = vec3<f32>(0.0);
+        let steps = cells.x + 2u * (ROWS - 1u);
+        for (var t = 0u; t < steps; t = t + 1u) {
+            if (row < cells.y && t >= lag && t - lag < cells.x) {
+                let x = t - lag;
+                let slot = (row % ERROR_ROWS) * cells.x + x;
+                let diffused = diffusion_errors[slot].rgb;
+                // 读取后清零，环形缓冲区的这一行之后会被复用
+                diffusion_errors[slot] = vec4<f32>(0.0);
+
+                let origin = vec2<u32>(x, row) * pixel_size;
+                let source = textureLoad(input_tex, vec2<i32>(origin), 0);
+                let color = source.rgb + diffused + carry1;
+                let quantized = quantize(color);
+                let error = color - quantized;
+
+                let xi = i32(x);
+                if (atkinson) {
+                    // 只扩散 6/8 的误差，对比度更高
+                    let e = error / 8.0;
+                    carry1 = carry2 + e;
+                    carry2 = e;
+                    add_error(xi - 1, row + 1u, cells, e);
+                    add_error(xi, row + 1u, cells, e);
+                    add_error(xi + 1, row + 1u, cells, e);
+                    add_error(xi, row + 2u, cells, e);
+                } else {
+                    carry1 = error * (7.0 / 16.0);
+                    add_error(xi - 1, row + 1u, cells, error * (3.0 / 16.0));
+                    add_error(xi, row + 1u, cells, error * (5.0 / 16.0));
+                    add_error(xi + 1, row + 1u, cells, error * (1.0 / 16.0));
+                }
+
+                let end = min(origin + pixel_size, img_size);
+                for (var py = origin.y; py < end.y; py = py + 1u) {
+                    for (var px = origin.x; px < end.x; px = px + 1u) {
+                        textureStore(output_tex, vec2<i32>(vec2<u32>(px, py)), vec4<f32>(quantized, source.a));
+                    }
+                }
+            }
+            storageBarrier();
+        }
+    }
+}
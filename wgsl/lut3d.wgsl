@@ -0,0 +1,20 @@
+struct InputParams {
+    // 0: 原图，1: 完全应用 LUT
+    intensity: f32,
+};
+
+///#include "common/group0+vs.wgsl"
+
+@group(0) @binding(4) var lut_tex: texture_3d<f32>;
+@group(0) @binding(5) var lut_sampler: sampler;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(tex, tex_sampler, vertex.uv);
+    // 0 与 1 对应第一个与最后一个格点的中心，格点之间由硬件三线性插值
+    let size = f32(textureDimensions(lut_tex).x);
+    let rgb = clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+    let coord = rgb * ((size - 1.0) / size) + 0.5 / size;
+    let graded = textureSample(lut_tex, lut_sampler, coord).rgb;
+    return vec4<f32>(mix(color.rgb, graded, params[0].intensity), color.a);
+}
@@ -0,0 +1,43 @@
+struct InputParams {
+    progress: f32,
+    // 0: crossfade, 1: wipe, 2: circular reveal, 3: split（对比模式的分割线）
+    kind: f32,
+    // wipe 推进的方向，split 中是分割线的法线
+    direction: vec2<f32>,
+    // 宽 / 高，保证圆形不被拉伸
+    aspect: f32,
+    // 过渡边缘的羽化宽度
+    feather: f32,
+};
+
+///#include "common/group0+vs.wgsl"
+
+// tex 是新滤镜的画面，from_tex 是旧滤镜的画面
+@group(0) @binding(4) var from_tex: texture_2d<f32>;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let p = params[0];
+    let from_color = textureSample(from_tex, tex_sampler, vertex.uv);
+    let to_color = textureSample(tex, tex_sampler, vertex.uv);
+
+    var factor = p.progress;
+    if (p.kind == 1.0) {
+        // 沿 direction 方向把 uv 投影到 [0, 1]，先经过的一侧先显示新画面
+        let d = dot(vertex.uv - 0.5, p.direction) + 0.5;
+        let edge = p.progress * (1.0 + p.feather);
+        factor = 1.0 - smoothstep(edge - p.feather, edge, d);
+    } else if (p.kind == 2.0) {
+        let scale = vec2<f32>(p.aspect, 1.0);
+        let dist = length((vertex.uv - 0.5) * scale);
+        let radius = p.progress * (length(scale * 0.5) + p.feather);
+        factor = 1.0 - smoothstep(radius - p.feather, radius, dist);
+    } else if (p.kind == 3.0) {
+        // 分割线之前是原图，之后是滤镜的画面，分割线约 2 个像素宽
+        let d = dot(vertex.uv, p.direction);
+        let on_line = 1.0 - smoothstep(0.0, fwidth(d) * 2.0, abs(d - p.progress));
+        let color = select(from_color, to_color, d > p.progress);
+        return mix(color, vec4<f32>(1.0), on_line);
+    }
+    return mix(from_color, to_color, factor);
+}
@@ -1,7 +1,6 @@
 struct InputParams {
-    ascii_width_uv: vec2<f32>,
-    ascii_width: f32,
-    half_aw: f32,
+    // 字符格子的边长（源纹理像素）
+    cell_size: f32,
 };
 
 struct VertexOutput {
@@ -11,6 +10,9 @@ struct VertexOutput {
 
 struct UniformData {
     mvp: mat4x4<f32>,
+    // 源纹理的裁剪区域：uv = uv_offset + texCoord * uv_scale
+    uv_offset: vec2<f32>,
+    uv_scale: vec2<f32>,
 };
 @group(0) @binding(0) var<uniform> mat_uniform: UniformData;
 
@@ -21,7 +23,7 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.position = mat_uniform.mvp * vec4<f32>(pos, 1.0);
-    out.uv = texCoord;
+    out.uv = mat_uniform.uv_offset + texCoord * mat_uniform.uv_scale;
     return out;
 }
 
@@ -29,6 +31,7 @@ fn vs_main(
 @group(0) @binding(2) var tex: texture_2d<f32>;
 @group(0) @binding(3) var tex_sampler: sampler;
 
+// https://www.shadertoy.com/view/lssGDj
 fn character(n: i32, p: vec2<f32>) -> f32 {
 	let np = floor(p * vec2<f32>(4.0, -4.0) + 2.5);
     if (clamp(np.x, 0.0, 4.0) == np.x) && (clamp(np.y, 0.0, 4.0) == np.y) {
@@ -43,7 +46,8 @@ fn character(n: i32, p: vec2<f32>) -> f32 {
 @fragment
 fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
     let params = params[0];
-    let uv = floor(vertex.uv / params.ascii_width_uv) * params.ascii_width_uv;
+    let cell_uv = vec2<f32>(params.cell_size) / vec2<f32>(textureDimensions(tex));
+    let uv = floor(vertex.uv / cell_uv) * cell_uv;
     let color = textureSample(tex, tex_sampler, uv).rgb;
     let gray = length(color);
 
@@ -56,6 +60,6 @@ fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
     if (gray > 0.7) { n = 4532799; }  // ∆
 	if (gray > 0.8) { n = 11512810; } // #
 	
-    let p = ((vertex.position.xy / params.half_aw) % 2.0) - vec2<f32>(1.0);
+    let p = ((vertex.position.xy / (params.cell_size * 0.5)) % 2.0) - vec2<f32>(1.0);
     return vec4<f32>(color * character(n, p), 1.0);
 }
@@ -0,0 +1,109 @@
+struct InputParams {
+    // 曝光补偿，单位 EV
+    exposure: f32,
+    contrast: f32,
+    saturation: f32,
+    // 只增强低饱和度的颜色
+    vibrance: f32,
+    highlights: f32,
+    shadows: f32,
+    // 正值偏暖（黄），负值偏冷（蓝）
+    temperature: f32,
+    // 正值偏品红，负值偏绿
+    tint: f32,
+    // 大于 1 提亮中间调
+    gamma: f32,
+    // 输入与输出纹理是否为 sRGB 格式，是的话 GPU 在采样与写入时已经做了转换
+    input_srgb: f32,
+    target_srgb: f32,
+};
+
+struct VertexOutput {
+    @location(0) uv: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+struct UniformData {
+    mvp: mat4x4<f32>,
+    // 源纹理的裁剪区域：uv = uv_offset + texCoord * uv_scale
+    uv_offset: vec2<f32>,
+    uv_scale: vec2<f32>,
+};
+@group(0) @binding(0) var<uniform> mat_uniform: UniformData;
+
+@vertex
+fn vs_main(
+    @location(0) pos: vec3<f32>,
+    @location(1) texCoord: vec2<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = mat_uniform.mvp * vec4<f32>(pos, 1.0);
+    out.uv = mat_uniform.uv_offset + texCoord * mat_uniform.uv_scale;
+    return out;
+}
+
+@group(0) @binding(1) var<storage> params : array<InputParams>;
+@group(0) @binding(2) var tex: texture_2d<f32>;
+@group(0) @binding(3) var tex_sampler: sampler;
+
+// 非 sRGB 格式的纹理中保存的是 sRGB 编码的颜色，调色需要先转换到线性空间
+
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    let lo = c / 12.92;
+    let hi = pow((c + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(hi, lo, c <= vec3<f32>(0.04045));
+}
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    let lo = c * 12.92;
+    let hi = 1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(hi, lo, c <= vec3<f32>(0.0031308));
+}
+
+// 线性 Rec.709 的相对亮度
+fn luminance(c: vec3<f32>) -> f32 {
+    return dot(c, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// 18% 灰，对比度围绕它在 log 空间中缩放
+let MIDDLE_GREY: f32 = 0.18;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let p = params[0];
+    let color = textureSample(tex, tex_sampler, vertex.uv);
+    var c = clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+    if (p.input_srgb == 0.0) {
+        c = srgb_to_linear(c);
+    }
+
+    // 白平衡：按通道增益，再归一化使亮度不变
+    var gains = vec3<f32>(1.0 + 0.3 * p.temperature, 1.0 - 0.3 * p.tint, 1.0 - 0.3 * p.temperature);
+    gains = max(gains, vec3<f32>(0.0));
+    c = c * (gains / max(luminance(gains), 0.0001));
+
+    c = c * exp2(p.exposure);
+
+    // 高光与阴影：按感知亮度分区，各自调整曝光，单位 EV
+    let tone = pow(clamp(luminance(c), 0.0, 1.0), 1.0 / 2.2);
+    let shadow_weight = 1.0 - smoothstep(0.0, 0.5, tone);
+    let highlight_weight = smoothstep(0.5, 1.0, tone);
+    c = c * exp2(p.shadows * shadow_weight + p.highlights * highlight_weight);
+
+    let k = max(1.0 + p.contrast, 0.01);
+    c = MIDDLE_GREY * pow(max(c, vec3<f32>(0.0)) / MIDDLE_GREY, vec3<f32>(k));
+
+    let l = luminance(c);
+    let max_c = max(c.r, max(c.g, c.b));
+    let sat = select(0.0, (max_c - min(c.r, min(c.g, c.b))) / max_c, max_c > 0.0);
+    let amount = 1.0 + p.saturation + p.vibrance * (1.0 - sat);
+    c = max(mix(vec3<f32>(l), c, amount), vec3<f32>(0.0));
+
+    c = pow(c, vec3<f32>(1.0 / max(p.gamma, 0.01)));
+
+    c = clamp(c, vec3<f32>(0.0), vec3<f32>(1.0));
+    if (p.target_srgb == 0.0) {
+        c = linear_to_srgb(c);
+    }
+    return vec4<f32>(c, color.a);
+}
@@ -1,11 +1,11 @@
 struct InputParams {
+    // How close together hatch lines should be placed
     density: f32,
-    half_density: f32,
-    width: f32,
-    hatch_1: f32,
-    hatch_2: f32,
-    hatch_3: f32,
-    hatch_4: f32,
+    // How wide hatch lines are drawn.
+    line_width: f32,
+    // The brightnesses at which different hatch lines appear
+    thresholds: array<f32, 4>,
+    edge_noise_suppression: f32,
     opaque_background_color: f32,
 };
 
@@ -16,6 +16,9 @@ struct VertexOutput {
 
 struct UniformData {
     mvp: mat4x4<f32>,
+    // 源纹理的裁剪区域：uv = uv_offset + texCoord * uv_scale
+    uv_offset: vec2<f32>,
+    uv_scale: vec2<f32>,
 };
 @group(0) @binding(0) var<uniform> mat_uniform: UniformData;
 
@@ -26,7 +29,7 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.position = mat_uniform.mvp * vec4<f32>(pos, 1.0);
-    out.uv = texCoord;
+    out.uv = mat_uniform.uv_offset + texCoord * mat_uniform.uv_scale;
     return out;
 }
 
@@ -38,31 +41,34 @@ fn edge_detection(luminance: f32, step_val: f32) -> f32 {
     return step(step_val, fwidth(luminance));
 }
 
+// https://www.shadertoy.com/view/MdX3Dr
 @fragment
 fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
     let color = textureSample(tex, tex_sampler, vertex.uv);
     let gray = length(color.rgb);
-    let black_edge = 1.0 - edge_detection(gray, 0.125);
+    let black_edge = 1.0 - edge_detection(gray, params[0].edge_noise_suppression);
 
     var frag_color = vec3<f32>(1.0);
     let frag_coord = vertex.position.xy;
 
     let param = params[0];
-    if (gray < param.hatch_1) && ((frag_coord.x + frag_coord.y) % param.density <= param.width) {
+    let half_density = param.density * 0.5;
+    if (gray < param.thresholds[0]) && ((frag_coord.x + frag_coord.y) % param.density <= param.line_width) {
         frag_color = vec3<f32>(gray);
     }
-    if (gray < param.hatch_2) && (abs((frag_coord.x - frag_coord.y)) % param.density <= param.width) {
+    if (gray < param.thresholds[1]) && (abs((frag_coord.x - frag_coord.y)) % param.density <= param.line_width) {
         frag_color = vec3<f32>(gray);
     }
      
-    if (gray < param.hatch_3) && (abs((frag_coord.x + frag_coord.y - param.half_density)) % param.density <= param.width) {
+    if (gray < param.thresholds[2]) && (abs((frag_coord.x + frag_coord.y - half_density)) % param.density <= param.line_width) {
         frag_color = vec3<f32>(gray);
     }
-    if (gray < param.hatch_4) && (abs((frag_coord.x - frag_coord.y - param.half_density)) % param.density <= param.width) {
+    if (gray < param.thresholds[3]) && (abs((frag_coord.x - frag_coord.y - half_density)) % param.density <= param.line_width) {
         frag_color = vec3<f32>(0.0);
     }
 
     frag_color = min(vec3<f32>(black_edge), frag_color);
+    // frag_color = frag_color * black_edge;
     var alpha = 1.0;
     if (param.opaque_background_color == 0.0) {
         alpha = 1.0 - step(0.3, length(frag_color));
@@ -0,0 +1,113 @@
+// dither.wgsl 与 dither_diffusion.wgsl 共用的参数与绑定
+struct DitherParams {
+    // 输出像素块的边长（源纹理像素）
+    pixel_size: f32,
+    // 0: Bayer，1: 蓝噪声，2: Floyd-Steinberg，3: Atkinson
+    method: f32,
+    // Bayer 矩阵的边长：2, 4 或 8
+    bayer_size: f32,
+    // 没有调色板时每个通道的色阶数
+    levels: f32,
+    // 有序抖动的阈值偏移幅度
+    spread: f32,
+    palette_len: f32,
+    // 每个 u32 是一个打包的 rgb8 颜色，r 在最低字节
+    palette: array<u32, 58>,
+};
+
+@group(0) @binding(0) var<storage, read> params: DitherParams;
+// 误差扩散的环形缓冲区，有序抖动不使用
+@group(0) @binding(1) var<storage, read_write> diffusion_errors: array<vec4<f32>>;
+@group(0) @binding(2) var output_tex: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(3) var input_tex: texture_2d<f32>;
+
+fn unpack_color(c: u32) -> vec3<f32> {
+    return vec3<f32>(f32(c & 0xffu), f32((c >> 8u) & 0xffu), f32((c >> 16u) & 0xffu)) / 255.0;
+}
+
+// 最接近的调色板颜色，没有调色板时按色阶量化
+fn quantize(color: vec3<f32>) -> vec3<f32> {
+    let len = u32(params.palette_len);
+    if (len == 0u) {
+        let steps = max(round(params.levels), 2.0) - 1.0;
+        return clamp(floor(color * steps + 0.5) / steps, vec3<f32>(0.0), vec3<f32>(1.0));
+    }
+    // 按人眼对各通道的敏感度加权的距离
+    let weights = vec3<f32>(0.299, 0.587, 0.114);
+    var best = unpack_color(params.palette[0]);
+    var best_distance = dot((color - best) * (color - best), weights);
+    for (var i = 1u; i < len; i = i + 1u) {
+        let candidate = unpack_color(params.palette[i]);
+        let distance = dot((color - candidate) * (color - candidate), weights);
+        if (distance < best_distance) {
+            best = candidate;
+            best_distance = distance;
+        }
+    }
+    return best;
+}
+
+// 16x16 的蓝噪声阈值矩阵（void-and-cluster 生成），每个 u32 打包 4 个 0..255 的排名
+let BLUE_NOISE: array<u32, 64> = array<u32, 64>(
+    0x13bc32eau, 0x2f79ab3au, 0x68f701a3u, 0x410e8416u, 0x617608d1u, 0xe417cdf0u, 0xaa7b408au, 0x9563e048u,
+    0xa5e58b55u, 0x546f924eu, 0xe71ed8b0u, 0xb42ac999u, 0x1dc33e19u, 0xf907b92bu, 0x30bf6429u, 0xf3800557u,
+    0xfd6598ddu, 0xc83bdc82u, 0x70880c9cu, 0x6d45aeffu, 0x4900bd2eu, 0x748e5aacu, 0x3dd2ed50u, 0xa0ce2193u,
+    0x71d97c51u, 0x1bf10fd0u, 0x14b22da8u, 0x12e160c1u, 0x233ca4f2u, 0x44b5359du, 0x537d69dfu, 0x8d3783ecu,
+    0x86e30ac5u, 0xc67e5ff6u, 0xa1f40394u, 0x6ab60947u, 0x4bb35d28u, 0x24da06c0u, 0x22ca395bu, 0x4ae99bd7u,
+    0x189678fcu, 0x77a63f6eu, 0x6785b7e8u, 0xa71f7531u, 0xee33d410u, 0x15fe89cfu, 0xfa0d974cu, 0x87cb58beu,
+    0xa952b866u, 0x34bb5926u, 0x43ad62ccu, 0x38de0481u, 0x7f0290e6u, 0x729a0be2u, 0x1cdb27efu, 0x4daf91ebu,
+    0x46f825c4u, 0xb142c76bu, 0x9f738f11u, 0x1a6c2c56u, 0xd69e5c7au, 0x5ef5208cu, 0x36c24fd5u, 0xa2fbbad3u
+);
+
+fn bayer_threshold(cell: vec2<u32>, size: f32) -> f32 {
+    var bits = 1u;
+    if (size >= 6.0) {
+        bits = 3u;
+    } else if (size >= 3.0) {
+        bits = 2u;
+    }
+    let n = 1u << bits;
+    let x = cell.x % n;
+    let y = cell.y % n;
+    // 交错 x ^ y 与 y 的各个位，低位在结果的高位
+    var v = 0u;
+    for (var i = 0u; i < bits; i = i + 1u) {
+        let xb = (x >> i) & 1u;
+        let yb = (y >> i) & 1u;
+        v = v | ((((xb ^ yb) << 1u) | yb) << (2u * (bits - 1u - i)));
+    }
+    return (f32(v) + 0.5) / f32(n * n);
+}
+
+fn blue_noise_threshold(cell: vec2<u32>) -> f32 {
+    // 常量数组只能用常量下标访问，先复制到变量中
+    var noise = BLUE_NOISE;
+    let i = (cell.y % 16u) * 16u + cell.x % 16u;
+    let rank = (noise[i / 4u] >> (8u * (i % 4u))) & 0xffu;
+    return (f32(rank) + 0.5) / 256.0;
+}
+
+@compute @workgroup_size(16, 16)
+fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let img_size = vec2<u32>(textureDimensions(input_tex));
+    let uv = global_invocation_id.xy;
+    if (uv.x >= img_size.x || uv.y >= img_size.y) {
+        return;
+    }
+
+    // 像素块内的所有像素都使用块中心的颜色
+    let pixel_size = u32(max(params.pixel_size, 1.0));
+    let cell = uv / pixel_size;
+    let center = min(cell * pixel_size + pixel_size / 2u, img_size - 1u);
+    let color = textureLoad(input_tex, vec2<i32>(center), 0);
+
+    var rgb = color.rgb;
+    let method = u32(params.method);
+    if (method == 0u) {
+        rgb = quantize(rgb + (bayer_threshold(cell, params.bayer_size) - 0.5) * params.spread);
+    } else if (method == 1u) {
+        rgb = quantize(rgb + (blue_noise_threshold(cell) - 0.5) * params.spread);
+    }
+    // 误差扩散在 dither_diffusion.wgsl 中逐行进行，这里只输出像素块的颜色
+    textureStore(output_tex, vec2<i32>(uv), vec4<f32>(rgb, color.a));
+}
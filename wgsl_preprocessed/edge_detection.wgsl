@@ -10,6 +10,9 @@ struct VertexOutput {
 
 struct UniformData {
     mvp: mat4x4<f32>,
+    // 源纹理的裁剪区域：uv = uv_offset + texCoord * uv_scale
+    uv_offset: vec2<f32>,
+    uv_scale: vec2<f32>,
 };
 @group(0) @binding(0) var<uniform> mat_uniform: UniformData;
 
@@ -20,7 +23,7 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.position = mat_uniform.mvp * vec4<f32>(pos, 1.0);
-    out.uv = texCoord;
+    out.uv = mat_uniform.uv_offset + texCoord * mat_uniform.uv_scale;
     return out;
 }
 
@@ -36,6 +39,7 @@ fn edge_detection(luminance: f32, step_val: f32) -> f32 {
 fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
     let color = textureSample(tex, tex_sampler, vertex.uv);
     let gray = length(color.rgb);
+    // let gray = (0.2126*color.r) + (0.7152*color.g) + (0.0722*color.b);
     let edge = edge_detection(gray, params[0].noise_suppression);
 
     if (params[0].opaque_background_color >= 1.0) {
@@ -0,0 +1,32 @@
+// https://software.intel.com/en-us/blogs/2014/07/15/an-investigation-of-fast-real-time-gpu-based-image-blur-algorithms
+
+struct BlurParams {
+  radius: f32,
+  sigma: f32,
+  // weights[i] 是与中心相距 i 个像素的权重，由 CPU 端根据 radius 与 sigma 计算
+  weights: array<f32>,
+};
+
+@group(0) @binding(0) var<storage, read> params: BlurParams;
+@group(0) @binding(1) var blur_xy: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(2) var swap_x: texture_2d<f32>;
+
+@compute @workgroup_size(16, 16)
+fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let img_size = vec2<i32>(textureDimensions(swap_x));
+  let uv = vec2<i32>(global_invocation_id.xy);
+  if (uv.x >= img_size.x || uv.y >= img_size.y) {
+    return;
+  }
+  // 在 metal(macOS, iOS)上，上面的共享缓存方案性能更差
+  let uv_zero = vec2<i32>(0, 0);
+  let uv_max = img_size - vec2<i32>(1, 1);
+  let radius = i32(params.radius);
+  var temp: vec4<f32> = textureLoad(swap_x, uv, 0) * params.weights[0];
+  for (var i: i32 = 1; i <= radius; i += 1) {
+    let uv_offset = vec2<i32>(0, i);
+    temp += textureLoad(swap_x, clamp(uv + uv_offset, uv_zero, uv_max), 0) * params.weights[i];
+    temp += textureLoad(swap_x, clamp(uv - uv_offset, uv_zero, uv_max), 0) * params.weights[i];
+  }
+  textureStore(blur_xy, uv, temp);
+}
@@ -0,0 +1,131 @@
+struct InputParams {
+    // 网点格子的边长（源纹理像素）
+    cell_size: f32,
+    // 0: 单色网屏，1: CMYK 分色，每个通道使用自己的网屏角度
+    cmyk: f32,
+    // 0: 圆点，1: 线条，2: 菱形
+    dot_shape: f32,
+    // 单色网屏的角度
+    angle: f32,
+    // C, M, Y, K 的网屏角度
+    angles: array<f32, 4>,
+    paper_color: array<f32, 3>,
+    ink_color: array<f32, 3>,
+    opaque_background_color: f32,
+};
+
+struct VertexOutput {
+    @location(0) uv: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+struct UniformData {
+    mvp: mat4x4<f32>,
+    // 源纹理的裁剪区域：uv = uv_offset + texCoord * uv_scale
+    uv_offset: vec2<f32>,
+    uv_scale: vec2<f32>,
+};
+@group(0) @binding(0) var<uniform> mat_uniform: UniformData;
+
+@vertex
+fn vs_main(
+    @location(0) pos: vec3<f32>,
+    @location(1) texCoord: vec2<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = mat_uniform.mvp * vec4<f32>(pos, 1.0);
+    out.uv = mat_uniform.uv_offset + texCoord * mat_uniform.uv_scale;
+    return out;
+}
+
+@group(0) @binding(1) var<storage> params : array<InputParams>;
+@group(0) @binding(2) var tex: texture_2d<f32>;
+@group(0) @binding(3) var tex_sampler: sampler;
+
+fn rotate(p: vec2<f32>, degrees: f32) -> vec2<f32> {
+    let a = radians(degrees);
+    let s = sin(a);
+    let c = cos(a);
+    return vec2<f32>(c * p.x - s * p.y, s * p.x + c * p.y);
+}
+
+// 网屏中 pixel 所在格子中心的 uv
+fn cell_center_uv(pixel: vec2<f32>, tex_size: vec2<f32>, cell_size: f32, angle: f32) -> vec2<f32> {
+    let screen = rotate(pixel, -angle) / cell_size;
+    let center = rotate((floor(screen) + 0.5) * cell_size, angle);
+    return center / tex_size;
+}
+
+// 网点对 pixel 的覆盖率，网点面积与 darkness 成正比
+fn dot_coverage(pixel: vec2<f32>, cell_size: f32, angle: f32, shape: f32, darkness: f32) -> f32 {
+    // 格子内以中心为原点的坐标，范围 [-1, 1]
+    let f = (fract(rotate(pixel, -angle) / cell_size) - 0.5) * 2.0;
+    let d = clamp(darkness, 0.0, 1.0);
+    var dist = length(f);
+    var radius = sqrt(4.0 * d / 3.14159265);
+    if (shape >= 1.5) {
+        dist = abs(f.x) + abs(f.y);
+        radius = sqrt(2.0 * d);
+    } else if (shape >= 0.5) {
+        dist = abs(f.y);
+        radius = d;
+    }
+    let aa = max(fwidth(dist), 0.0001);
+    return 1.0 - smoothstep(radius - aa, radius + aa, dist);
+}
+
+// 从 rgb 中分离出 c, m, y, k
+fn separate_cmyk(rgb: vec3<f32>) -> vec4<f32> {
+    let k = 1.0 - max(rgb.r, max(rgb.g, rgb.b));
+    if (k >= 0.9999) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    let cmy = (vec3<f32>(1.0) - rgb - k) / (1.0 - k);
+    return vec4<f32>(cmy, k);
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let p = params[0];
+    let tex_size = vec2<f32>(textureDimensions(tex));
+    let pixel = vertex.uv * tex_size;
+    let cell_size = max(p.cell_size, 1.0);
+    let paper = vec3<f32>(p.paper_color[0], p.paper_color[1], p.paper_color[2]);
+    let ink = vec3<f32>(p.ink_color[0], p.ink_color[1], p.ink_color[2]);
+
+    var coverage: f32;
+    // 白纸上的油墨颜色，透明背景时作为颜色输出
+    var inked: vec3<f32>;
+    if (p.cmyk >= 0.5) {
+        // 每个网屏在自己格子的中心采样
+        var amounts: array<f32, 4>;
+        for (var i = 0; i < 4; i = i + 1) {
+            let uv = cell_center_uv(pixel, tex_size, cell_size, params[0].angles[i]);
+            let cmyk = separate_cmyk(textureSample(tex, tex_sampler, uv).rgb);
+            amounts[i] = dot_coverage(pixel, cell_size, params[0].angles[i], p.dot_shape, cmyk[i]);
+        }
+        let c = amounts[0];
+        let m = amounts[1];
+        let y = amounts[2];
+        let k = amounts[3];
+        // 油墨叠印是相乘的
+        inked = mix(vec3<f32>(1.0), vec3<f32>(0.0, 1.0, 1.0), c)
+            * mix(vec3<f32>(1.0), vec3<f32>(1.0, 0.0, 1.0), m)
+            * mix(vec3<f32>(1.0), vec3<f32>(1.0, 1.0, 0.0), y)
+            * mix(vec3<f32>(1.0), ink, k);
+        coverage = 1.0 - (1.0 - c) * (1.0 - m) * (1.0 - y) * (1.0 - k);
+    } else {
+        let uv = cell_center_uv(pixel, tex_size, cell_size, p.angle);
+        let gray = dot(textureSample(tex, tex_sampler, uv).rgb, vec3<f32>(0.299, 0.587, 0.114));
+        coverage = dot_coverage(pixel, cell_size, p.angle, p.dot_shape, 1.0 - gray);
+        inked = mix(vec3<f32>(1.0), ink, coverage);
+    }
+
+    if (p.opaque_background_color >= 1.0) {
+        return vec4<f32>(paper * inked, 1.0);
+    } else {
+        // 只输出油墨，纸张透明
+        let color = select(ink, inked, p.cmyk >= 0.5);
+        return vec4<f32>(color, coverage);
+    }
+}
@@ -0,0 +1,46 @@
+struct InputParams {
+    // 0: 原图，1: 完全应用 LUT
+    intensity: f32,
+};
+
+struct VertexOutput {
+    @location(0) uv: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+struct UniformData {
+    mvp: mat4x4<f32>,
+    // 源纹理的裁剪区域：uv = uv_offset + texCoord * uv_scale
+    uv_offset: vec2<f32>,
+    uv_scale: vec2<f32>,
+};
+@group(0) @binding(0) var<uniform> mat_uniform: UniformData;
+
+@vertex
+fn vs_main(
+    @location(0) pos: vec3<f32>,
+    @location(1) texCoord: vec2<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = mat_uniform.mvp * vec4<f32>(pos, 1.0);
+    out.uv = mat_uniform.uv_offset + texCoord * mat_uniform.uv_scale;
+    return out;
+}
+
+@group(0) @binding(1) var<storage> params : array<InputParams>;
+@group(0) @binding(2) var tex: texture_2d<f32>;
+@group(0) @binding(3) var tex_sampler: sampler;
+
+@group(0) @binding(4) var lut_tex: texture_3d<f32>;
+@group(0) @binding(5) var lut_sampler: sampler;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(tex, tex_sampler, vertex.uv);
+    // 0 与 1 对应第一个与最后一个格点的中心，格点之间由硬件三线性插值
+    let size = f32(textureDimensions(lut_tex).x);
+    let rgb = clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+    let coord = rgb * ((size - 1.0) / size) + 0.5 / size;
+    let graded = textureSample(lut_tex, lut_sampler, coord).rgb;
+    return vec4<f32>(mix(color.rgb, graded, params[0].intensity), color.a);
+}
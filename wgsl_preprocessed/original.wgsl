@@ -9,6 +9,9 @@ struct VertexOutput {
 
 struct UniformData {
     mvp: mat4x4<f32>,
+    // 源纹理的裁剪区域：uv = uv_offset + texCoord * uv_scale
+    uv_offset: vec2<f32>,
+    uv_scale: vec2<f32>,
 };
 @group(0) @binding(0) var<uniform> mat_uniform: UniformData;
 
@@ -19,7 +22,7 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.position = mat_uniform.mvp * vec4<f32>(pos, 1.0);
-    out.uv = texCoord;
+    out.uv = mat_uniform.uv_offset + texCoord * mat_uniform.uv_scale;
     return out;
 }
 
@@ -0,0 +1,59 @@
+struct InputParams {
+    // 色阶：输入的黑场、白场与中间调 gamma
+    black_point: f32,
+    white_point: f32,
+    gamma: f32,
+};
+
+struct VertexOutput {
+    @location(0) uv: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+struct UniformData {
+    mvp: mat4x4<f32>,
+    // 源纹理的裁剪区域：uv = uv_offset + texCoord * uv_scale
+    uv_offset: vec2<f32>,
+    uv_scale: vec2<f32>,
+};
+@group(0) @binding(0) var<uniform> mat_uniform: UniformData;
+
+@vertex
+fn vs_main(
+    @location(0) pos: vec3<f32>,
+    @location(1) texCoord: vec2<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = mat_uniform.mvp * vec4<f32>(pos, 1.0);
+    out.uv = mat_uniform.uv_offset + texCoord * mat_uniform.uv_scale;
+    return out;
+}
+
+@group(0) @binding(1) var<storage> params : array<InputParams>;
+@group(0) @binding(2) var tex: texture_2d<f32>;
+@group(0) @binding(3) var tex_sampler: sampler;
+
+// 每个像素的 rgb 是对应输入值经过曲线后的输出
+@group(0) @binding(4) var curve_tex: texture_2d<f32>;
+@group(0) @binding(5) var curve_sampler: sampler;
+
+fn curve_coord(value: f32, width: f32) -> vec2<f32> {
+    // 0 与 1 对应第一个与最后一个像素的中心
+    return vec2<f32>(value * ((width - 1.0) / width) + 0.5 / width, 0.5);
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let p = params[0];
+    let color = textureSample(tex, tex_sampler, vertex.uv);
+
+    let range = max(p.white_point - p.black_point, 0.0001);
+    var rgb = clamp((color.rgb - p.black_point) / range, vec3<f32>(0.0), vec3<f32>(1.0));
+    rgb = pow(rgb, vec3<f32>(1.0 / max(p.gamma, 0.01)));
+
+    let width = f32(textureDimensions(curve_tex).x);
+    let r = textureSample(curve_tex, curve_sampler, curve_coord(rgb.r, width)).r;
+    let g = textureSample(curve_tex, curve_sampler, curve_coord(rgb.g, width)).g;
+    let b = textureSample(curve_tex, curve_sampler, curve_coord(rgb.b, width)).b;
+    return vec4<f32>(r, g, b, color.a);
+}